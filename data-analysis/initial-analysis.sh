@@ -87,7 +87,7 @@ run_jq_programs_if_needed() {
     # A list of all unsafe contexts in a crate, stored by crate. They are
     # blocks (not indexed blocks), so they include unsafe blocks and root
     # blocks of unsafe functions.
-    # {name,contexts: [{size, unsaf, contexts}]}
+    # {name,contexts: [{size, unsaf, contents}]}
     [unsafe_contexts.json]='{name, "contexts": ([.functions[] | select(.unsaf) | .block] + [.functions[] | blocks | select(is_unsafe_block)])}'
 
     # Lists all the closures which have their own unsafe uses, for each crate.
@@ -150,6 +150,14 @@ run_jq_programs_if_needed() {
     [unsafe_context_counts.txt]=unsafe_contexts.json
     )
 
+    # If the Rust `analyze` tool is built and on the PATH, it makes all the
+    # missing files in one (parallel) pass over $all_data, and the loop below
+    # finds them already done.
+    if command -v analyze > /dev/null 2>&1; then
+        echo "Running \`analyze process\` over \`$all_data\` ..."
+        analyze process "$all_data"
+    fi
+
     # Generate all the files from jq commands.
     for file in "${files[@]}"; do
         pgm="${files_to_programs[$file]}"
@@ -248,7 +256,7 @@ MDTable () {
 # Makes a MD table of unsafe uses, counted by what type of macro they came from
 # (if any) and what type of use they are.
 if [ ! -f uses_type_cross_origin.txt ]; then
-    MDTable unsafe_uses.json ' `derive` macro ! External macro ! Local macro ! Not a macro ! All sources ' 'include "./unsafe"; select(is_origin_derive_macro)!include "./unsafe"; select(is_origin_external_macro)!include "./unsafe"; select(is_origin_local_macro)!include "./unsafe"; select(is_origin_not_macro)!.' 'Deref ptr ! Call unsafe Rust function ! Call FFI ! Use `static mut` ! Use inline ASM ! All uses' 'include "./unsafe"; select(is_indexed_deref)!include "./unsafe"; select(is_indexed_unsafe_rust_call)!include "./unsafe"; select(is_indexed_ffi_call)!include "./unsafe"; select(is_indexed_mut_static)!include "./unsafe"; select(is_indexed_inline_asm)!.' Source > uses_type_cross_origin.txt
+    MDTable unsafe_uses.json ' `derive` macro ! External macro ! Local macro ! Not a macro ! All sources ' 'include "./unsafe"; select(is_origin_derive_macro)!include "./unsafe"; select(is_origin_external_macro)!include "./unsafe"; select(is_origin_local_macro)!include "./unsafe"; select(is_origin_not_macro)!.' 'Deref ptr ! Call unsafe Rust function ! Call FFI ! Call intrinsic ! Use `static mut` ! Use extern `static` ! Use inline ASM ! All uses' 'include "./unsafe"; select(is_indexed_deref)!include "./unsafe"; select(is_indexed_unsafe_rust_call)!include "./unsafe"; select(is_indexed_ffi_call)!include "./unsafe"; select(is_indexed_intrinsic_call)!include "./unsafe"; select(is_indexed_mut_static)!include "./unsafe"; select(is_indexed_extern_static)!include "./unsafe"; select(is_indexed_inline_asm)!.' Source > uses_type_cross_origin.txt
 fi
 
 
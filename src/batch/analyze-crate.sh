@@ -4,6 +4,7 @@ main () {
     crate_name="$1"
     output_file="../../output/$crate_name.out"
     assert_nz "$crate_name"
+    export UAST_OUTPUT="$(pwd)/output/uast"
     cd sources
     if [[ ! -a "$crate_name" ]]; then
         eval cargo clone $crate_name > /dev/null
@@ -18,6 +18,10 @@ jobs = $procs_per_cargo" > sources/.cargo/config
     fi
 
     cat $filename | xargs -n 1 --max-procs="$cargos" sh analyze-crate.sh
+
+    # `analyze-crate.sh` writes a UAST file for each target to `output/uast`. The analyses read
+    # them all, a line each, from `json.out`.
+    find output/uast -name '*.json' -exec cat {} + > output/json.out
 }
 
 
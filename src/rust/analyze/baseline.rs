@@ -0,0 +1,131 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Keeps a baseline of the unsafe sites of crates, and checks UASTs against it (see `baseline.rs`
+// in the library)
+//
+//     analyze baseline FILE BASELINE
+//
+// writes the unsafe sites of the UASTs in FILE to BASELINE, keeping the review notes of those
+// already in it, and
+//
+//     analyze check [--strict] FILE BASELINE
+//
+// reports how the sites in FILE differ from BASELINE, failing if any are new (or, with
+// `--strict`, if any haven't been reviewed), which is meant for CI. The UASTs in FILE must have
+// the snippet length BASELINE was made with.
+
+use unsafe_ast::atomic;
+use unsafe_ast::baseline::{self,Baseline,Entry,Site};
+
+use input;
+
+use std::fs::File;
+use std::io::{self,BufReader,Write};
+use std::path::Path;
+
+pub const BASELINE_USAGE: &'static str = "analyze baseline FILE BASELINE";
+pub const CHECK_USAGE: &'static str = "analyze check [--strict] FILE BASELINE";
+
+/// The unsafe sites of the UASTs in `path`, and the snippet length the UASTs have.
+fn sites(path: &str) -> Result<(Vec<Site>, Option<u64>), String> {
+    let crates = try!(input::crates(path));
+    let snippet_length = try!(baseline::snippet_length(&crates));
+    Ok((crates.iter().flat_map(baseline::sites).collect(), snippet_length))
+}
+
+fn read(path: &Path) -> io::Result<Baseline> {
+    Baseline::read(BufReader::new(try!(File::open(path))))
+}
+
+/// Writes the baseline of the UASTs in `args[0]` to `args[1]`, returning the number of sites in
+/// it and how many of them have been reviewed.
+pub fn write_baseline(args: &[String]) -> Result<(usize, usize), String> {
+    if args.len() != 2 {
+        return Err(format!("Usage: {}", BASELINE_USAGE));
+    }
+    let path = Path::new(&args[1]);
+    let previous = if path.exists() {
+        Some(try!(read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))))
+    } else {
+        None
+    };
+    let (sites, snippet_length) = try!(sites(&args[0]));
+    let baseline = Baseline::new(&sites, snippet_length, previous.as_ref());
+    // A failed write leaves the old baseline
+    try!(atomic::write_atomically(path, |out| baseline.write(out))
+             .map_err(|e| format!("Could not write {}: {}", path.display(), e)));
+    let reviewed = baseline.entries().iter().filter(|entry| entry.note().is_some()).count();
+    Ok((baseline.entries().len(), reviewed))
+}
+
+fn site_line(site: &Site) -> String {
+    let mut line = format!("{:016x} {} in {}::{} at {}", site.fingerprint(), site.kind(),
+                           site.krate(), site.function(), site.span());
+    if !site.snippet().is_empty() {
+        line.push_str(": ");
+        line.push_str(site.snippet());
+    }
+    line
+}
+
+fn entry_line(entry: &Entry) -> String {
+    let mut line = format!("{:016x} {} in {}::{}", entry.fingerprint(), entry.kind(),
+                           entry.krate(), entry.function());
+    if !entry.snippet().is_empty() {
+        line.push_str(": ");
+        line.push_str(entry.snippet());
+    }
+    line
+}
+
+/// Checks the UASTs in FILE against BASELINE, printing a report to `out`.
+pub fn check<W: Write>(args: &[String], out: &mut W) -> Result<(), String> {
+    let strict = args.iter().any(|arg| arg == "--strict");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--strict").collect();
+    if paths.len() != 2 {
+        return Err(format!("Usage: {}", CHECK_USAGE));
+    }
+    let path = Path::new(paths[1]);
+    let baseline = try!(read(path).map_err(|e| {
+        format!("Could not read the baseline {} ({}), which `analyze baseline` writes",
+                path.display(), e)
+    }));
+    let (sites, snippet_length) = try!(sites(paths[0]));
+    let check = try!(baseline.check(&sites, snippet_length).map_err(|e| {
+        format!("Could not check against {}: {}", path.display(), e)
+    }));
+
+    let mut report = String::new();
+    if !check.new_sites().is_empty() {
+        report.push_str("New unsafe sites:\n");
+        for site in check.new_sites() {
+            report.push_str(&format!("+ {}\n", site_line(site)));
+        }
+    }
+    if !check.unreviewed().is_empty() {
+        report.push_str("Unreviewed unsafe sites:\n");
+        for entry in check.unreviewed() {
+            report.push_str(&format!("? {}\n", entry_line(entry)));
+        }
+    }
+    if !check.gone().is_empty() {
+        report.push_str("Unsafe sites no longer there:\n");
+        for entry in check.gone() {
+            report.push_str(&format!("- {}\n", entry_line(entry)));
+        }
+    }
+    report.push_str(&format!("{} unsafe sites: {} new, {} unreviewed, {} no longer there\n",
+                             sites.len(), check.new_sites().len(), check.unreviewed().len(),
+                             check.gone().len()));
+    try!(out.write_all(report.as_bytes()).and_then(|_| out.flush())
+            .map_err(|e| format!("Could not write output: {}", e)));
+
+    if !check.new_sites().is_empty() {
+        Err(format!("{} unsafe sites are not in {} (add them with `analyze baseline` once \
+                     they have been reviewed)", check.new_sites().len(), path.display()))
+    } else if strict && !check.unreviewed().is_empty() {
+        Err(format!("{} unsafe sites in {} have not been reviewed",
+                    check.unreviewed().len(), path.display()))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Converts UASTs between JSON lines and the binary encoding (see `binary.rs`)
+//
+//     analyze to-binary [FILE]
+//     analyze to-json [FILE]
+//
+// read FILE (or stdin) and write the converted UASTs to stdout. JSON comes out in the default
+// encoding, one UAST per line, as `emit-ast` writes it.
+
+use unsafe_ast;
+use unsafe_ast::binary;
+
+use rustc_serialize::json;
+
+use std::io::{BufRead,Read,Write};
+
+/// Converts JSON lines to a binary file, returning the number of UASTs converted.
+pub fn to_binary<R: BufRead, W: Write>(input: R, output: &mut W) -> Result<u64, String> {
+    try!(binary::write_header(output).map_err(|e| format!("Could not write output: {}", e)));
+    let mut count = 0;
+    for (n, line) in input.lines().enumerate() {
+        let line = try!(line.map_err(|e| format!("Could not read line {}: {}", n + 1, e)));
+        if line.trim().is_empty() {
+            continue;
+        }
+        let krate = try!(unsafe_ast::decode(&line)
+                             .map_err(|e| format!("Could not decode line {}: {}", n + 1, e)));
+        try!(binary::write_record(output, &binary::encode(&krate))
+                 .map_err(|e| format!("Could not write output: {}", e)));
+        count += 1;
+    }
+    try!(output.flush().map_err(|e| format!("Could not write output: {}", e)));
+    Ok(count)
+}
+
+/// Converts a binary file to JSON lines, returning the number of UASTs converted.
+pub fn to_json<R: Read, W: Write>(input: R, output: &mut W) -> Result<u64, String> {
+    let records = try!(binary::records(input).map_err(|e| format!("Could not read input: {}", e)));
+    let mut count = 0;
+    for (n, record) in records.enumerate() {
+        let krate = try!(record.and_then(|record| binary::decode(&record))
+                             .map_err(|e| format!("Could not decode record {}: {}", n + 1, e)));
+        let json = json::encode(&krate).expect("The UAST should encode as JSON");
+        try!(writeln!(output, "{}", json).map_err(|e| format!("Could not write output: {}", e)));
+        count += 1;
+    }
+    try!(output.flush().map_err(|e| format!("Could not write output: {}", e)));
+    Ok(count)
+}
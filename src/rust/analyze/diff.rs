@@ -0,0 +1,39 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Reports what changed about `unsafe` between two UASTs (see `diff.rs` in the library)
+//
+//     analyze diff [--json] OLD NEW
+//
+// OLD and NEW are files holding one UAST each, such as two versions of a crate pulled out of
+// `json.out` with `analyze lookup`.
+
+use unsafe_ast::Crate;
+use unsafe_ast::diff::CrateDiff;
+
+use input;
+
+use std::io::Write;
+
+pub const USAGE: &'static str = "analyze diff [--json] OLD NEW";
+
+fn only_crate(path: &str) -> Result<Crate, String> {
+    let mut crates = try!(input::crates(path));
+    match crates.len() {
+        1 => Ok(crates.remove(0)),
+        n => Err(format!("{} holds {} UASTs, but diff compares one with another \
+                          (pick them out with `analyze lookup`)", path, n)),
+    }
+}
+
+pub fn run<W: Write>(args: &[String], out: &mut W) -> Result<(), String> {
+    let json = args.iter().any(|arg| arg == "--json");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--json").collect();
+    if paths.len() != 2 {
+        return Err(format!("Usage: {}", USAGE));
+    }
+    let old = try!(only_crate(paths[0]));
+    let new = try!(only_crate(paths[1]));
+    let diff = CrateDiff::new(&old, &new);
+    let report = if json { format!("{}\n", diff.to_json()) } else { diff.to_text() };
+    out.write_all(report.as_bytes()).and_then(|_| out.flush())
+       .map_err(|e| format!("Could not write output: {}", e))
+}
@@ -0,0 +1,42 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Writes an HTML report of the unsafe code in the UASTs of a file (see `html.rs` in the library)
+//
+//     analyze html [-o DIR] FILE SRC
+//
+// reads the UASTs of one package's targets from FILE, and its source from the directory SRC (the
+// directory with its `Cargo.toml`, which the spans are relative to), and writes the report to DIR
+// (`unsafe-report` by default). Open `DIR/index.html` to browse it.
+
+use unsafe_ast::html::Report;
+
+use input;
+
+use std::path::Path;
+
+pub const USAGE: &'static str = "analyze html [-o DIR] FILE SRC";
+
+const DEFAULT_DIR: &'static str = "unsafe-report";
+
+/// Writes the report, returning the directory it is in and the source files which weren't found.
+pub fn run(args: &[String]) -> Result<(String, Vec<String>), String> {
+    let mut dir = DEFAULT_DIR.to_string();
+    let mut paths = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "-o" => match args.next() {
+                Some(d) => dir = d.clone(),
+                None => return Err(format!("-o takes a directory\nUsage: {}", USAGE)),
+            },
+            _ => paths.push(arg),
+        }
+    }
+    if paths.len() != 2 {
+        return Err(format!("Usage: {}", USAGE));
+    }
+    let crates = try!(input::crates(paths[0]));
+    let report = Report::new(&crates);
+    let missing = try!(report.write(Path::new(paths[1]), Path::new(&dir))
+                             .map_err(|e| format!("Could not write the report to {}: {}", dir, e)));
+    Ok((dir, missing))
+}
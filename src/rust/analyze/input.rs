@@ -0,0 +1,47 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Reading whole UAST files, as JSON lines or binary UASTs (see `binary.rs`)
+
+use unsafe_ast::{self,Crate};
+use unsafe_ast::binary;
+
+use std::fs::File;
+use std::io::{self,BufRead,BufReader};
+
+/// The UASTs in the file `path`, or stdin for "-".
+pub fn crates(path: &str) -> Result<Vec<Crate>, String> {
+    if path == "-" {
+        let stdin = io::stdin();
+        let lock = stdin.lock();
+        read_crates(lock).map_err(|e| format!("stdin: {}", e))
+    } else {
+        let file = try!(File::open(path).map_err(|e| format!("Could not open {}: {}", path, e)));
+        read_crates(BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+fn read_crates<R: BufRead>(mut input: R) -> Result<Vec<Crate>, String> {
+    let is_binary = {
+        let start = try!(input.fill_buf().map_err(|e| format!("Could not read input: {}", e)));
+        binary::is_binary(start)
+    };
+    let mut crates = vec![];
+    if is_binary {
+        let records = try!(binary::records(input).map_err(|e| e.to_string()));
+        for (n, record) in records.enumerate() {
+            let krate = try!(record.and_then(|record| binary::decode(&record))
+                                 .map_err(|e| format!("Could not decode record {}: {}", n + 1, e)));
+            crates.push(krate);
+        }
+    } else {
+        for (n, line) in input.lines().enumerate() {
+            let line = try!(line.map_err(|e| format!("Could not read line {}: {}", n + 1, e)));
+            if line.trim().is_empty() {
+                continue;
+            }
+            let krate = try!(unsafe_ast::decode(&line)
+                                 .map_err(|e| format!("Could not decode line {}: {}", n + 1, e)));
+            crates.push(krate);
+        }
+    }
+    Ok(crates)
+}
@@ -0,0 +1,65 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Looks crates up in a UAST file by name, through its index (see `index.rs`)
+//
+//     analyze index FILE
+//
+// writes the index `FILE.idx`, and
+//
+//     analyze lookup FILE NAME[@VERSION]...
+//
+// prints the UASTs of the named crates (of the given version, or of any) as JSON lines, in the
+// order they are in FILE.
+
+use unsafe_ast::index::Index;
+
+use rustc_serialize::json;
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Indexes `data`, returning the number of UASTs indexed.
+pub fn index(data: &Path) -> Result<usize, String> {
+    let index = try!(Index::build(data)
+                         .map_err(|e| format!("Could not index {}: {}", data.display(), e)));
+    try!(index.write(data).map_err(|e| format!("Could not write {}: {}",
+                                               Index::path(data).display(), e)));
+    Ok(index.entries().len())
+}
+
+/// Prints the UASTs of the crates in `queries`, each `NAME` or `NAME@VERSION`.
+pub fn lookup<W: Write>(data: &Path, queries: &[String], out: &mut W) -> Result<(), String> {
+    let index = try!(Index::read(data).map_err(|e| {
+        format!("Could not read the index of {} ({}), which `analyze index` writes",
+                data.display(), e)
+    }));
+    let mut entries = vec![];
+    for query in queries {
+        let mut parts = query.splitn(2, '@');
+        let name = parts.next().unwrap_or("");
+        let version = parts.next();
+        let found = index.find(name, version);
+        if found.is_empty() {
+            return Err(format!("No crate {} in {}", query, data.display()));
+        }
+        entries.extend(found);
+    }
+    entries.sort_by_key(|entry| entry.offset());
+    entries.dedup();
+
+    let mut file = try!(File::open(data)
+                            .map_err(|e| format!("Could not open {}: {}", data.display(), e)));
+    for entry in entries {
+        let bad_entry = |e| format!("Could not read {} from {}: {}", entry.name(),
+                                    data.display(), e);
+        let line = if index.is_binary() {
+            let krate = try!(index.load(&mut file, entry).map_err(&bad_entry));
+            json::encode(&krate).expect("The UAST should encode as JSON").into_bytes()
+        } else {
+            try!(index.line(&mut file, entry).map_err(&bad_entry)).into_bytes()
+        };
+        try!(out.write_all(&line).and_then(|_| out.write_all(b"\n"))
+                 .map_err(|e| format!("Could not write output: {}", e)));
+    }
+    out.flush().map_err(|e| format!("Could not write output: {}", e))
+}
@@ -0,0 +1,171 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Tool for running the analyses of `data-analysis/unsafe.jq` over UASTs, without jq
+//
+//     analyze <analysis> [FILE]
+//
+// reads line-delimited input from FILE (or stdin), and prints what
+//
+//     jq -c 'include "unsafe"; <analysis>'
+//
+// would. Analyses of blocks take one block per line, and the others one UAST per line.
+//
+//     analyze process [-j JOBS] [-o DIR] [-f] FILE [OUTPUT...]
+//
+// writes the files of `run_jq_programs_if_needed` in `data-analysis/initial-analysis.sh` (see
+// `process.rs`), and
+//
+//     analyze to-binary [FILE]
+//     analyze to-json [FILE]
+//
+// convert UASTs between JSON lines and the binary encoding (see `convert.rs`), and
+//
+//     analyze index FILE
+//     analyze lookup FILE NAME[@VERSION]...
+//
+// index FILE by crate, and print the UASTs of the named crates (see `lookup.rs`), and
+//
+//     analyze diff [--json] OLD NEW
+//
+// reports what changed about `unsafe` from one UAST to another (see `diff.rs`), and
+//
+//     analyze baseline FILE BASELINE
+//     analyze check [--strict] FILE BASELINE
+//
+// record the unsafe sites of UASTs, and fail when new ones turn up (see `baseline.rs`), and
+//
+//     analyze summary [--json] [--top N] [FILE]
+//
+// prints an overview of the unsafe code in each UAST (see `summary.rs`), and
+//
+//     analyze html [-o DIR] FILE SRC
+//
+// writes an HTML report with the unsafe code highlighted in the source (see `html.rs`), and
+//
+//     analyze sarif [--src-root URI] [FILE]
+//
+// prints the unsafe sites of UASTs as a SARIF log, for code-scanning tools (see `sarif.rs`).
+
+extern crate rustc_serialize;
+extern crate unsafe_ast;
+
+mod baseline;
+mod convert;
+mod diff;
+mod html;
+mod input;
+mod lookup;
+mod process;
+mod sarif;
+mod summary;
+
+use unsafe_ast::analysis::{self,Analysis};
+use unsafe_ast::outputs;
+
+use std::fs::File;
+use std::io::{self,BufRead,BufReader,BufWriter,Write};
+use std::path::Path;
+
+macro_rules! errln(
+    ($($arg:tt)*) => { {
+        let r = writeln!(&mut ::std::io::stderr(), $($arg)*);
+        r.expect("failed printing to stderr");
+    } }
+);
+
+fn usage() -> ! {
+    errln!("Usage: analyze <analysis> [FILE]");
+    errln!("       {}", process::USAGE);
+    errln!("       analyze to-binary [FILE]");
+    errln!("       analyze to-json [FILE]");
+    errln!("       analyze index FILE");
+    errln!("       analyze lookup FILE NAME[@VERSION]...");
+    errln!("       {}", diff::USAGE);
+    errln!("       {}", baseline::BASELINE_USAGE);
+    errln!("       {}", baseline::CHECK_USAGE);
+    errln!("       {}", summary::USAGE);
+    errln!("       {}", html::USAGE);
+    errln!("       {}", sarif::USAGE);
+    errln!("");
+    errln!("Analyses of blocks:");
+    for analysis in analysis::ANALYSES.iter().filter(|a| a.takes_block()) {
+        errln!("    {}", analysis.name());
+    }
+    errln!("Analyses of UASTs:");
+    for analysis in analysis::ANALYSES.iter().filter(|a| !a.takes_block()) {
+        errln!("    {}", analysis.name());
+    }
+    errln!("Outputs of process:");
+    for output in outputs::OUTPUTS {
+        errln!("    {}", output.file_name());
+    }
+    std::process::exit(2);
+}
+
+fn run<R: BufRead>(analysis: Analysis, input: R) -> Result<(), String> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for (n, line) in input.lines().enumerate() {
+        let line = try!(line.map_err(|e| format!("Could not read line {}: {}", n + 1, e)));
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = try!(analysis.run(&line)
+                                  .map_err(|e| format!("Could not decode line {}: {}", n + 1, e)));
+        try!(writeln!(out, "{}", result).map_err(|e| format!("Could not write output: {}", e)));
+    }
+    Ok(())
+}
+
+/// FILE, or stdin if there is no FILE.
+fn input(path: Option<&String>) -> Result<Box<BufRead>, String> {
+    match path {
+        Some(path) => match File::open(path) {
+            Ok(file) => Ok(Box::new(BufReader::new(file))),
+            Err(e) => Err(format!("Could not open {}: {}", path, e)),
+        },
+        None => Ok(Box::new(BufReader::new(io::stdin()))),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let stdout = io::stdout();
+    let result = match args.get(1).map(|arg| &arg[..]) {
+        Some("process") => process::Options::from_args(&args[2..])
+            .and_then(|options| process::run(&options))
+            .map(|_| ()),
+        Some("to-binary") => input(args.get(2)).and_then(|input| {
+            convert::to_binary(input, &mut BufWriter::new(stdout.lock())).map(|_| ())
+        }),
+        Some("to-json") => input(args.get(2)).and_then(|input| {
+            convert::to_json(input, &mut BufWriter::new(stdout.lock())).map(|_| ())
+        }),
+        Some("index") if args.len() == 3 => lookup::index(Path::new(&args[2])).map(|n| {
+            errln!("Indexed {} UASTs", n);
+        }),
+        Some("lookup") if args.len() >= 4 => {
+            lookup::lookup(Path::new(&args[2]), &args[3..], &mut BufWriter::new(stdout.lock()))
+        },
+        Some("diff") => diff::run(&args[2..], &mut BufWriter::new(stdout.lock())),
+        Some("baseline") => baseline::write_baseline(&args[2..]).map(|(sites, reviewed)| {
+            errln!("Wrote {} unsafe sites, {} of them reviewed", sites, reviewed);
+        }),
+        Some("check") => baseline::check(&args[2..], &mut BufWriter::new(stdout.lock())),
+        Some("summary") => summary::run(&args[2..], &mut BufWriter::new(stdout.lock())),
+        Some("sarif") => sarif::run(&args[2..], &mut BufWriter::new(stdout.lock())),
+        Some("html") => html::run(&args[2..]).map(|(dir, missing)| {
+            for file in missing {
+                errln!("Could not read {}, so it has no page", file);
+            }
+            errln!("Wrote {}/index.html", dir);
+        }),
+        name => match name.and_then(Analysis::from_name) {
+            Some(analysis) => input(args.get(2)).and_then(|input| run(analysis, input)),
+            None => usage(),
+        },
+    };
+    if let Err(e) = result {
+        errln!("analyze: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,269 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Writes the files of `run_jq_programs_if_needed` in one pass over `json.out`
+//
+//     analyze process [-j JOBS] [-o DIR] [-f] FILE [OUTPUT...]
+//
+// The UASTs in FILE (or stdin, for "-"), as JSON lines or a binary UAST file (see `binary.rs`), are
+// read once, and decoded and analyzed by JOBS worker threads. A writer thread puts the results
+// back in input order, so each file comes out just as jq would write it. Each output is written to
+// a temporary file in DIR (by default, the current directory), which is moved into place once
+// every UAST has been processed.
+//
+// By default, like `run_jq_programs_if_needed`, only the outputs which don't exist in DIR yet are
+// written. Naming OUTPUTs (by file name), or passing -f, writes them regardless.
+
+use unsafe_ast::{self,Crate};
+use unsafe_ast::atomic::AtomicFile;
+use unsafe_ast::binary;
+use unsafe_ast::jq;
+use unsafe_ast::outputs::{self,Output};
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self,BufRead,BufReader,Read,Write};
+use std::path::PathBuf;
+use std::sync::{Arc,Mutex};
+use std::sync::mpsc::{self,Receiver,SyncSender};
+use std::thread;
+
+/// The lines of each output for one UAST, or why it couldn't be analyzed.
+type Analyzed = Result<Vec<Vec<String>>, String>;
+
+/// Why reading stopped early. The workers stop only if the writer has, or they panicked.
+const WORKERS_STOPPED: &'static str = "The workers stopped before all of the input was read";
+
+/// One undecoded UAST, with its number in the input (for errors).
+enum Record {
+    Line(usize, String),
+    Binary(usize, Vec<u8>),
+}
+
+impl Record {
+    /// Decodes the UAST, and for a line, parses it as jq reads it too, so that parts of it can be
+    /// printed as they were read. A binary record is printed as it decodes.
+    fn decode(&self) -> Result<(Crate, Option<jq::Value>), String> {
+        match *self {
+            Record::Line(n, ref line) => {
+                let error = |e: &Display| format!("Could not decode line {}: {}", n, e);
+                let krate = try!(unsafe_ast::decode(line).map_err(|e| error(&e)));
+                let read = try!(jq::parse(line).map_err(|e| error(&e)));
+                Ok((krate, Some(read)))
+            },
+            Record::Binary(n, ref record) => binary::decode(record)
+                .map(|krate| (krate, None))
+                .map_err(|e| format!("Could not decode record {}: {}", n, e)),
+        }
+    }
+}
+
+pub struct Options {
+    jobs: usize,
+    dir: PathBuf,
+    force: bool,
+    input: String,
+    outputs: Vec<Output>,
+}
+
+pub const USAGE: &'static str = "analyze process [-j JOBS] [-o DIR] [-f] FILE [OUTPUT...]";
+
+impl Options {
+    pub fn from_args(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            jobs: default_jobs(),
+            dir: PathBuf::from("."),
+            force: false,
+            input: String::new(),
+            outputs: vec![],
+        };
+        let mut input = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match &arg[..] {
+                "-j" => {
+                    let jobs = try!(args.next().ok_or("-j needs a number of jobs".to_string()));
+                    options.jobs = match jobs.parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => return Err(format!("Bad number of jobs: {}", jobs)),
+                    };
+                },
+                "-o" => {
+                    let dir = try!(args.next().ok_or("-o needs a directory".to_string()));
+                    options.dir = PathBuf::from(dir);
+                },
+                "-f" => options.force = true,
+                _ if input.is_none() => input = Some(arg.clone()),
+                _ => {
+                    let output = try!(Output::from_file_name(arg)
+                                          .ok_or(format!("Unknown output: {}", arg)));
+                    options.outputs.push(output);
+                },
+            }
+        }
+        options.input = try!(input.ok_or("No input file given".to_string()));
+        if options.outputs.is_empty() {
+            let dir = options.dir.clone();
+            let force = options.force;
+            options.outputs = outputs::OUTPUTS.iter()
+                .filter(|output| force || !dir.join(output.file_name()).exists())
+                .cloned()
+                .collect();
+        }
+        Ok(options)
+    }
+}
+
+/// The number of processors, going by `/proc/cpuinfo`, or 4 if that can't be read.
+fn default_jobs() -> usize {
+    let mut cpuinfo = String::new();
+    let read = File::open("/proc/cpuinfo").and_then(|mut f| f.read_to_string(&mut cpuinfo));
+    let processors = cpuinfo.lines().filter(|line| line.starts_with("processor")).count();
+    if read.is_ok() && processors > 0 { processors } else { 4 }
+}
+
+/// Runs the analyses, returning the number of UASTs processed.
+pub fn run(options: &Options) -> Result<u64, String> {
+    if options.outputs.is_empty() {
+        return Ok(0);
+    }
+    if options.input == "-" {
+        let stdin = io::stdin();
+        let lock = stdin.lock();
+        process(lock, options)
+    } else {
+        let file = try!(File::open(&options.input)
+                            .map_err(|e| format!("Could not open {}: {}", options.input, e)));
+        process(BufReader::new(file), options)
+    }
+}
+
+fn process<R: BufRead>(mut input: R, options: &Options) -> Result<u64, String> {
+    let is_binary = {
+        let start = try!(input.fill_buf().map_err(|e| format!("Could not read input: {}", e)));
+        binary::is_binary(start)
+    };
+    // Moved into place only once every UAST has been written, and removed otherwise
+    let mut files = vec![];
+    for output in &options.outputs {
+        let path = options.dir.join(output.file_name());
+        let file = try!(AtomicFile::create(&path)
+                            .map_err(|e| format!("Could not create {}: {}", path.display(), e)));
+        files.push(file);
+    }
+
+    // Bounded, so that reading doesn't run far ahead of the workers
+    let (record_tx, record_rx) = mpsc::sync_channel::<(u64, Record)>(options.jobs * 16);
+    let record_rx = Arc::new(Mutex::new(record_rx));
+    let (result_tx, result_rx) = mpsc::channel::<(u64, Analyzed)>();
+
+    let workers: Vec<_> = (0..options.jobs).map(|_| {
+        let record_rx = record_rx.clone();
+        let result_tx = result_tx.clone();
+        let outputs = options.outputs.clone();
+        thread::spawn(move || work(record_rx, result_tx, outputs))
+    }).collect();
+    // Only the workers hold the ends of the channels, so that each side sees the other stop
+    drop(record_rx);
+    drop(result_tx);
+    let writer = thread::spawn(move || write(result_rx, files));
+
+    let read = if is_binary {
+        read_binary(input, record_tx)
+    } else {
+        read_lines(input, record_tx)
+    };
+    let mut worker_panicked = false;
+    for worker in workers {
+        worker_panicked |= worker.join().is_err();
+    }
+    let written = writer.join().unwrap_or(Err("The writer thread panicked".to_string()));
+    // Reading stops early only if the workers or the writer did, so their errors say why
+    if worker_panicked {
+        return Err("A worker thread panicked".to_string());
+    }
+    let (written, files) = try!(written);
+    try!(read);
+    for file in files {
+        let path = file.path().to_path_buf();
+        try!(file.commit()
+                 .map_err(|e| format!("Could not move {} into place: {}", path.display(), e)));
+    }
+    Ok(written)
+}
+
+/// Sends each non-empty line of `input` to the workers, numbered by its position among them.
+/// Fails if the workers have stopped.
+fn read_lines<R: BufRead>(input: R, records: SyncSender<(u64, Record)>) -> Result<(), String> {
+    let mut seq = 0;
+    for (n, line) in input.lines().enumerate() {
+        let line = try!(line.map_err(|e| format!("Could not read line {}: {}", n + 1, e)));
+        if line.trim().is_empty() {
+            continue;
+        }
+        if records.send((seq, Record::Line(n + 1, line))).is_err() {
+            return Err(WORKERS_STOPPED.to_string());
+        }
+        seq += 1;
+    }
+    Ok(())
+}
+
+/// Like `read_lines`, for a binary UAST file.
+fn read_binary<R: BufRead>(input: R, records: SyncSender<(u64, Record)>) -> Result<(), String> {
+    let binary = try!(binary::records(input).map_err(|e| format!("Could not read input: {}", e)));
+    for (n, record) in binary.enumerate() {
+        let record = try!(record.map_err(|e| format!("Could not read record {}: {}", n + 1, e)));
+        if records.send((n as u64, Record::Binary(n + 1, record))).is_err() {
+            return Err(WORKERS_STOPPED.to_string());
+        }
+    }
+    Ok(())
+}
+
+fn work(records: Arc<Mutex<Receiver<(u64, Record)>>>,
+        results: mpsc::Sender<(u64, Analyzed)>,
+        outputs: Vec<Output>) {
+    loop {
+        let next = records.lock().map(|records| records.recv());
+        let (seq, record) = match next {
+            Ok(Ok(job)) => job,
+            _ => return,
+        };
+        let analyzed = record.decode().map(|(krate, read)| {
+            let printer = match read {
+                Some(ref read) => jq::Printer::as_read(&krate, read),
+                None => jq::Printer::new(),
+            };
+            outputs.iter().map(|output| output.lines(&krate, &printer)).collect()
+        });
+        if results.send((seq, analyzed)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Writes the results to `files` in input order, returning the number of UASTs written, and the
+/// files to commit. Stops at the first UAST which couldn't be analyzed, and fails if the results
+/// of any UAST never came.
+fn write(results: Receiver<(u64, Analyzed)>, mut files: Vec<AtomicFile>)
+         -> Result<(u64, Vec<AtomicFile>), String> {
+    let mut pending = BTreeMap::new();
+    let mut next = 0;
+    for (seq, analyzed) in results {
+        pending.insert(seq, analyzed);
+        while let Some(analyzed) = pending.remove(&next) {
+            let lines = try!(analyzed);
+            for (file, lines) in files.iter_mut().zip(lines) {
+                for line in lines {
+                    try!(writeln!(file, "{}", line)
+                             .map_err(|e| format!("Could not write output: {}", e)));
+                }
+            }
+            next += 1;
+        }
+    }
+    if !pending.is_empty() {
+        return Err(format!("The workers stopped before analyzing UAST {}", next + 1));
+    }
+    Ok((next, files))
+}
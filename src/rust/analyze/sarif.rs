@@ -0,0 +1,40 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Prints the unsafe sites of the UASTs in a file as a SARIF log (see `sarif.rs` in the library)
+//
+//     analyze sarif [--src-root URI] [FILE]
+//
+// reads UASTs from FILE (or stdin). The paths of a crate's source files are relative to its
+// directory, which is `%SRCROOT%` in the log. `--src-root` gives that directory's absolute URI,
+// like `file:///home/me/repo/crates/foo/`, which SARIF requires it to be.
+
+use unsafe_ast::sarif;
+
+use input;
+
+use std::io::Write;
+
+pub const USAGE: &'static str = "analyze sarif [--src-root URI] [FILE]";
+
+pub fn run<W: Write>(args: &[String], out: &mut W) -> Result<(), String> {
+    let mut src_root = None;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--src-root" => match args.next() {
+                Some(root) if sarif::is_absolute_uri(root) => src_root = Some(root),
+                Some(root) => {
+                    return Err(format!("--src-root takes an absolute URI, like \
+                                        file:///home/me/repo/crates/foo/, not {}", root))
+                },
+                None => return Err(format!("--src-root takes a URI\nUsage: {}", USAGE)),
+            },
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("Usage: {}", USAGE)),
+        }
+    }
+    let crates = try!(input::crates(path.map_or("-", |p| &p[..])));
+    let log = sarif::log(&crates, src_root.map(|root| &root[..]));
+    write!(out, "{}\n", log.pretty()).and_then(|_| out.flush())
+        .map_err(|e| format!("Could not write output: {}", e))
+}
@@ -0,0 +1,49 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Prints a summary of the unsafe code in each UAST of a file (see `summary.rs` in the library)
+//
+//     analyze summary [--json] [--top N] [FILE]
+//
+// reads UASTs from FILE (or stdin), and prints a report on each, or a JSON object per line with
+// `--json`. The densest N functions (10 by default) are listed.
+
+use unsafe_ast::summary::{self,Summary};
+
+use input;
+
+use std::io::Write;
+
+pub const USAGE: &'static str = "analyze summary [--json] [--top N] [FILE]";
+
+pub fn run<W: Write>(args: &[String], out: &mut W) -> Result<(), String> {
+    let mut json = false;
+    let mut top = summary::DEFAULT_TOP;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--json" => json = true,
+            "--top" => {
+                top = match args.next().map(|n| n.parse()) {
+                    Some(Ok(n)) => n,
+                    _ => return Err(format!("--top takes a number\nUsage: {}", USAGE)),
+                };
+            },
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("Usage: {}", USAGE)),
+        }
+    }
+    let crates = try!(input::crates(path.map_or("-", |p| &p[..])));
+    for (n, krate) in crates.iter().enumerate() {
+        let summary = Summary::new(krate, top);
+        let report = if json {
+            format!("{}\n", summary.to_json())
+        } else if n > 0 {
+            format!("\n{}", summary.to_text())
+        } else {
+            summary.to_text()
+        };
+        try!(out.write_all(report.as_bytes())
+                .map_err(|e| format!("Could not write output: {}", e)));
+    }
+    out.flush().map_err(|e| format!("Could not write output: {}", e))
+}
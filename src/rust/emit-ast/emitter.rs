@@ -0,0 +1,650 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// The visitor which produces the Unsafe AST of a crate
+
+use rustc::hir;
+use rustc::hir::{intravisit,Unsafety};
+use rustc::hir::def::Def;
+use rustc::hir::def_id::DefId;
+use rustc::hir::map as hir_map;
+use rustc::middle::privacy::AccessLevels;
+use rustc::session::Session;
+use rustc::ty;
+use syntax::{abi,ast};
+
+use syntax::codemap::{CodeMap,ExpnInfo,ExpnFormat,Span};
+
+use options::Options;
+
+use unsafe_ast::{Block,Callee,Crate,FFI,FnDecl,Impl,Indexed,MacroOrigin,Metadata,Module,Signature,
+                 SpanInfo,UASTNode,Unsafe,UnsafeImpl,UnsafeTrait,Visibility};
+
+use std::collections::HashMap;
+use std::mem;
+
+// ================================================== //
+// Building the parts of the UAST from rustc's types  //
+// ================================================== //
+
+/// Create a new indexed item with
+///     `index` - its statement number in the enclosing block
+///     `span` - span of the item
+///     `codemap` - used to interpret the span
+///     `snippet_length` - the most characters of code snippet to include, if limited
+///     `macro_origin` - whether this item originated in a macro
+///     `structured_span` - whether to include a `SpanInfo` for the span
+fn indexed<T>(index: u64,
+              item: T,
+              span: Span,
+              codemap: &CodeMap,
+              snippet_length: Option<usize>,
+              macro_origin: MacroOrigin,
+              structured_span: bool) -> Indexed<T> {
+    let span_string = codemap.span_to_string(span);
+    let span_info = if structured_span { Some(span_info(span, codemap)) } else { None };
+    let mut snippet = codemap.span_to_snippet(span).unwrap_or_else(|_| String::new());
+    let mut truncated = false;
+    if let Some(length) = snippet_length {
+        if snippet.chars().count() > length {
+            snippet = snippet.chars().take(length).collect();
+            // With snippets disabled, every snippet is empty rather than cut short
+            truncated = length > 0;
+        }
+    }
+    Indexed::new(index, item, span_string, span_info, snippet, truncated, macro_origin)
+}
+
+fn span_info(span: Span, codemap: &CodeMap) -> SpanInfo {
+    let lo = codemap.lookup_char_pos(span.lo);
+    let hi = codemap.lookup_char_pos(span.hi);
+    SpanInfo::new(lo.file.name.clone(),
+                  lo.line as u64,
+                  lo.col.0 as u64 + 1,
+                  hi.line as u64,
+                  hi.col.0 as u64 + 1,
+                  codemap.lookup_byte_offset(span.lo).pos.0 as u64,
+                  codemap.lookup_byte_offset(span.hi).pos.0 as u64)
+}
+
+fn visibility(vis: Option<&hir::Visibility>) -> Visibility {
+    match vis {
+        Some(&hir::Visibility::Public) => Visibility::Public,
+        Some(&hir::Visibility::Crate) => Visibility::Crate,
+        Some(&hir::Visibility::Restricted { ref path, .. }) =>
+            Visibility::Restricted(hir::print::path_to_string(path)),
+        Some(&hir::Visibility::Inherited) | None => Visibility::Inherited,
+    }
+}
+
+/// Summarize the signature of a fn with
+///     `decl` - its argument and return types
+///     `abi` - its declared ABI
+///     `generics` - its own generic parameters (not those of an enclosing impl)
+fn signature(decl: &hir::FnDecl, abi: abi::Abi, generics: &hir::Generics) -> Signature {
+    let (output, raw_ptr_output) = match decl.output {
+        hir::FunctionRetTy::Return(ref ty) => (hir::print::ty_to_string(ty),
+                                               contains_raw_ptr(ty)),
+        hir::FunctionRetTy::DefaultReturn(_) => ("()".to_string(), false),
+        hir::FunctionRetTy::NoReturn(_) => (output_to_string(decl), false),
+    };
+    Signature::new(abi.name().to_string(),
+                   decl.inputs.iter().map(|arg| hir::print::ty_to_string(&arg.ty)).collect(),
+                   output,
+                   decl.inputs.iter().any(|arg| contains_raw_ptr(&arg.ty)),
+                   raw_ptr_output,
+                   generics.ty_params.len() as u64)
+}
+
+/// The return type of `decl` as rustc prints it, without the arrow.
+fn output_to_string(decl: &hir::FnDecl) -> String {
+    let output = hir::print::to_string(|s| s.print_fn_output(decl));
+    output.trim().trim_left_matches("->").trim().to_string()
+}
+
+fn ffi(h: abi::Abi) -> FFI {
+    let is_intrinsic = match h {
+        abi::Abi::RustIntrinsic | abi::Abi::PlatformIntrinsic => true,
+        _ => false,
+    };
+    let is_ffi = match h {
+        abi::Abi::Rust | abi::Abi::RustCall => false,
+        _ => !is_intrinsic,
+    };
+    FFI::new(is_ffi, is_intrinsic, h.name().to_string())
+}
+
+fn ffi_from_fn_ty(ty: ty::Ty) -> FFI {
+    match ty.sty {
+        ty::TyFnDef(_, _, ref f) |
+        ty::TyFnPtr(ref f) => ffi(f.abi),
+        ty::TyClosure(..) => ffi(abi::Abi::RustCall),
+        // Not something rustc gives an ABI for, so it is recorded as unknown
+        _ => FFI::new(false, false, String::new()),
+    }
+}
+
+fn unsafe_from_fn_ty(ty: ty::Ty) -> Unsafe {
+    match ty.sty {
+        ty::TyFnDef(_, _, ref f) |
+        ty::TyFnPtr(ref f) => Unsafe::new(is_unsafe(f.unsafety)),
+        _ => Unsafe::new(false),
+    }
+}
+
+fn is_unsafe(h: Unsafety) -> bool {
+    match h {
+        Unsafety::Normal => false,
+        Unsafety::Unsafe => true,
+    }
+}
+
+/// Finds raw pointer types anywhere within a type, e.g. `*const u8` or `Option<&[*mut T]>`.
+struct RawPtrFinder {
+    found: bool,
+}
+
+impl<'v> intravisit::Visitor<'v> for RawPtrFinder {
+    fn visit_ty(&mut self, t: &'v hir::Ty) {
+        if let hir::TyPtr(_) = t.node {
+            self.found = true;
+        }
+        intravisit::walk_ty(self, t);
+    }
+}
+
+fn contains_raw_ptr(t: &hir::Ty) -> bool {
+    let mut finder = RawPtrFinder { found: false };
+    intravisit::Visitor::visit_ty(&mut finder, t);
+    finder.found
+}
+
+/// Whether a field access on a value of type `ty` is a union field access, looking through the
+/// references and boxes that field access auto-derefs.
+fn is_union_ty(ty: ty::Ty) -> bool {
+    match ty.sty {
+        ty::TyUnion(..) => true,
+        ty::TyRef(_, ty::TypeAndMut { ty, .. }) |
+        ty::TyBox(ty) => is_union_ty(ty),
+        _ => false,
+    }
+}
+
+// ========================================= //
+// The Visitor which produces the Unsafe AST //
+// ========================================= //
+
+pub struct UnsafeASTEmitter<'a, 'tcx: 'a,'ast> {
+    tcx: ty::TyCtxt<'a, 'tcx, 'tcx>,
+    index: u64,
+    session: &'ast Session,
+    access_levels: &'ast AccessLevels,
+    contents: Vec<Indexed<UASTNode>>,
+    stack: Vec<(u64, Vec<Indexed<UASTNode>>)>,
+    crate_name: String,
+    crate_type: String,
+    options: Options,
+    functions: Vec<FnDecl>,
+    /// The node id of each fn in `functions`
+    function_ids: Vec<ast::NodeId>,
+    unsafe_impls: Vec<UnsafeImpl>,
+    unsafe_traits: Vec<UnsafeTrait>,
+    /// Module items and their spans, by the id of their parent module
+    modules: HashMap<ast::NodeId, Vec<(ast::NodeId, Span)>>,
+    /// Impl items, by the id of their parent module
+    impls: HashMap<ast::NodeId, Vec<(ast::NodeId, Impl)>>,
+    /// The union field expression written by the assignment being visited (see
+    /// `union_field_written`), which was already registered as a write and should not also be
+    /// registered as a read.
+    union_write: Option<ast::NodeId>,
+}
+
+impl<'a,'tcx:'a,'ast> UnsafeASTEmitter<'a,'tcx,'ast> {
+    pub fn new(tcx: ty::TyCtxt<'a,'tcx,'tcx>,
+               session: &'ast Session,
+               access_levels: &'ast AccessLevels,
+               crate_name: String,
+               crate_type: String,
+               options: Options) -> UnsafeASTEmitter<'a,'tcx,'ast> {
+        UnsafeASTEmitter {
+            tcx: tcx,
+            session: session,
+            access_levels: access_levels,
+            index: 0,
+            contents: vec![],
+            stack: vec![],
+            crate_name: crate_name,
+            crate_type: crate_type,
+            options: options,
+            functions: vec![],
+            function_ids: vec![],
+            unsafe_impls: vec![],
+            unsafe_traits: vec![],
+            modules: HashMap::new(),
+            impls: HashMap::new(),
+            union_write: None,
+        }
+    }
+
+    /// Produces the crate so far, described by `meta`.
+    pub fn into_uast(self, meta: Metadata) -> Crate {
+        let module_tree = if self.options.module_tree {
+            let root_span = self.tcx.map.span(ast::CRATE_NODE_ID);
+            Some(self.module_tree(ast::CRATE_NODE_ID, root_span, &mut self.functions_by_parent()))
+        } else {
+            None
+        };
+        Crate::new(self.crate_name,
+                   self.crate_type,
+                   self.functions,
+                   self.unsafe_impls,
+                   self.unsafe_traits,
+                   module_tree,
+                   meta)
+    }
+
+    /// Groups the positions of the registered functions by their enclosing impl, or if they are
+    /// not in an impl, by their enclosing module.
+    fn functions_by_parent(&self) -> HashMap<ast::NodeId, Vec<u64>> {
+        let mut by_parent = HashMap::new();
+        for (index, &id) in self.function_ids.iter().enumerate() {
+            let parent = self.tcx.map.get_parent(id);
+            let in_impl = self.impls.values().any(|impls| impls.iter().any(|&(i, _)| i == parent));
+            let parent = if in_impl { parent } else { self.tcx.map.get_module_parent(id) };
+            by_parent.entry(parent).or_insert(vec![]).push(index as u64);
+        }
+        by_parent
+    }
+
+    /// Builds the tree of registered modules and impls rooted at module `id`, taking each
+    /// module's and impl's functions out of `functions`.
+    fn module_tree(&self,
+                   id: ast::NodeId,
+                   span: Span,
+                   functions: &mut HashMap<ast::NodeId, Vec<u64>>) -> Module {
+        let mut impls = vec![];
+        for &(impl_id, ref imp) in self.impls.get(&id).map(|v| &v[..]).unwrap_or(&[]) {
+            impls.push(Impl::new(imp.self_ty().to_string(),
+                                 imp.trait_path().map(|path| path.to_string()),
+                                 imp.unsaf(),
+                                 imp.span().to_string(),
+                                 functions.remove(&impl_id).unwrap_or(vec![])));
+        }
+        let mut modules = vec![];
+        for &(module_id, module_span) in self.modules.get(&id).map(|v| &v[..]).unwrap_or(&[]) {
+            modules.push(self.module_tree(module_id, module_span, functions));
+        }
+        Module::new(self.tcx.node_path_str(id),
+                    self.session.codemap().span_to_string(span),
+                    functions.remove(&id).unwrap_or(vec![]),
+                    impls,
+                    modules)
+    }
+
+    /// Create a new indexed item with
+    ///     `index` - its statement number in the enclosing block
+    ///     `span` - span of the item
+    /// and store it in the list of registered unsafe points.
+    pub fn register_point(&mut self, item: UASTNode, span: Span) {
+        let macro_origin = self.get_macro_origin(span);
+        self.contents.push(
+            indexed(self.index,
+                    item,
+                    span,
+                    self.session.codemap(),
+                    self.options.snippet_length,
+                    macro_origin,
+                    self.options.structured_spans)
+        )
+    }
+
+    /// Register the AST for a completed function/method with
+    ///     `boxed_block` - its block
+    ///     `unsafety` - whether the fn is safe
+    ///     `name` - the name of the fn
+    ///     `span`
+    ///     `sig` - a summary of its signature
+    ///     `vis` - its written visibility
+    ///     `id` - its node id
+    pub fn register_function(&mut self,
+                             boxed_block: Box<Block>,
+                             unsafety: hir::Unsafety,
+                             name: String,
+                             span: Span,
+                             sig: Signature,
+                             vis: Visibility,
+                             id: ast::NodeId) {
+        let macro_origin = self.get_macro_origin(span);
+        let codemap = self.session.codemap();
+        let span_string = codemap.span_to_string(span);
+        let span_info = if self.options.structured_spans {
+            Some(span_info(span, codemap))
+        } else {
+            None
+        };
+        let exported = self.access_levels.is_exported(id);
+        self.functions.push(FnDecl::new(boxed_block,
+                                        is_unsafe(unsafety),
+                                        name,
+                                        span_string,
+                                        span_info,
+                                        macro_origin,
+                                        sig,
+                                        vis,
+                                        exported));
+        self.function_ids.push(id);
+    }
+
+    /// Register a module or impl item, so that the functions inside it can be grouped under it.
+    pub fn register_container(&mut self, i: &hir::Item) {
+        match i.node {
+            hir::ItemMod(_) => {
+                let parent = self.tcx.map.get_module_parent(i.id);
+                self.modules.entry(parent).or_insert(vec![]).push((i.id, i.span));
+            },
+            hir::ItemImpl(unsafety, _, _, ref trait_ref, ref self_ty, _) => {
+                let parent = self.tcx.map.get_module_parent(i.id);
+                let trait_path = trait_ref.as_ref().map(|t| self.trait_path(t));
+                let span_string = self.session.codemap().span_to_string(i.span);
+                // Its functions are filled in once they have all been registered
+                let imp = Impl::new(hir::print::ty_to_string(self_ty),
+                                    trait_path,
+                                    is_unsafe(unsafety),
+                                    span_string,
+                                    vec![]);
+                self.impls.entry(parent).or_insert(vec![]).push((i.id, imp));
+            },
+            _ => {},
+        }
+    }
+
+    /// Register an `unsafe impl` of a trait with
+    ///     `trait_ref` - the trait being implemented
+    ///     `self_ty` - the type the trait is implemented for, as a string
+    ///     `span`
+    pub fn register_unsafe_impl(&mut self,
+                                trait_ref: &hir::TraitRef,
+                                self_ty: String,
+                                span: Span) {
+        let macro_origin = self.get_macro_origin(span);
+        let span_string = self.session.codemap().span_to_string(span);
+        let trait_path = self.trait_path(trait_ref);
+        self.unsafe_impls.push(UnsafeImpl::new(trait_path, self_ty, span_string, macro_origin));
+    }
+
+    /// Register the declaration of an `unsafe trait` with
+    ///     `id` - the node id of the trait item
+    ///     `span`
+    pub fn register_unsafe_trait(&mut self, id: ast::NodeId, span: Span) {
+        let macro_origin = self.get_macro_origin(span);
+        let span_string = self.session.codemap().span_to_string(span);
+        let path = self.tcx.node_path_str(id);
+        self.unsafe_traits.push(UnsafeTrait::new(path, span_string, macro_origin));
+    }
+
+    /// The full path of the trait referred to.
+    fn trait_path(&self, trait_ref: &hir::TraitRef) -> String {
+        self.tcx.item_path_str(self.tcx.expect_def(trait_ref.ref_id).def_id())
+    }
+
+    /// The full path of the called fn/method, and the name of the crate defining it.
+    fn callee(&self, def_id: DefId) -> Callee {
+        Callee::new(self.tcx.item_path_str(def_id), self.tcx.crate_name(def_id.krate).to_string())
+    }
+
+    /// The union field which assigning to `lhs` writes, if any: in `u.f.g = x`, where `u` is the
+    /// union, that is `u.f`, found by walking down the left-hand side to the field of a union.
+    fn union_field_written<'e>(&self, lhs: &'e hir::Expr) -> Option<&'e hir::Expr> {
+        match lhs.node {
+            hir::ExprField(ref base, _) if is_union_ty(self.tcx.expr_ty_adjusted(base)) =>
+                Some(lhs),
+            hir::ExprField(ref base, _) |
+            hir::ExprTupField(ref base, _) |
+            hir::ExprIndex(ref base, _) => self.union_field_written(base),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the item is declared in an `extern` block, in this crate or another.
+    fn is_foreign_item(&self, def_id: DefId) -> bool {
+        match self.tcx.map.as_local_node_id(def_id) {
+            Some(node_id) => match self.tcx.map.find(node_id) {
+                Some(hir_map::NodeForeignItem(_)) => true,
+                _ => false,
+            },
+            None => self.tcx.sess.cstore.is_foreign_item(def_id),
+        }
+    }
+
+    /// Returns true if this `expn_info` was expanded by any macro.
+    /// This function taken from `clippy`
+    fn in_macro(&self, span: Span) -> bool {
+        self.session.codemap().with_expn_info(span.expn_id, |info| info.is_some())
+    }
+
+    /// Returns true if the macro that expanded the crate was outside of the current crate or was a
+    /// compiler plugin.
+    /// This function taken from `clippy`
+    fn in_external_macro(&self, span: Span) -> bool {
+        /// Invokes `in_macro` with the expansion info of the given span slightly heavy, try to use
+        /// this after other checks have already happened.
+        fn in_macro_ext(codemap: &CodeMap, opt_info: Option<&ExpnInfo>) -> bool {
+            // no ExpnInfo = no macro
+            opt_info.map_or(false, |info| {
+                if let ExpnFormat::MacroAttribute(..) = info.callee.format {
+                    // these are all plugins
+                    return true;
+                }
+                // no span for the callee = external macro
+                info.callee.span.map_or(true, |span| {
+                    // no snippet = external macro or compiler-builtin expansion
+                    codemap.span_to_snippet(span).ok()
+                        .map_or(true, |code| !code.starts_with("macro_rules"))
+                })
+            })
+        }
+        let codemap = self.session.codemap();
+        codemap.with_expn_info(span.expn_id, |info| in_macro_ext(codemap, info))
+    }
+
+    /// Determines whether a span originates within a macro, and if so, what type (Local, External,
+    /// Generated by Derive)
+    fn get_macro_origin(&self, span: Span) -> MacroOrigin {
+        if self.in_macro(span) {
+            if self.in_external_macro(span) { 
+                let span_string = self.session.codemap()
+                    .span_to_snippet(span).unwrap_or_else(|_| String::new());
+                // This is somewhat hacky, but when the compiler expands derive attributes it
+                // leaves the trait name as the snippet.
+                if span_string == "Clone" ||
+                   span_string == "Hash" ||
+                   span_string == "RustcEncodable" ||
+                   span_string == "RustcDecodable" ||
+                   span_string == "PartialEq" ||
+                   span_string == "Eq" ||
+                   span_string == "PartialOrd" ||
+                   span_string == "Ord" ||
+                   span_string == "Debug" ||
+                   span_string == "Default" ||
+                   span_string == "Send" ||
+                   span_string == "Sync" ||
+                   span_string == "Copy" ||
+                   span_string == "Encodable" ||
+                   span_string == "Decodable"
+                { MacroOrigin::DeriveMacro }
+                else { MacroOrigin::ExternalMacro }
+            }
+            else { MacroOrigin::LocalMacro }
+        } else { MacroOrigin::NotMacro }
+    }
+}
+
+impl<'a, 'tcx: 'a, 'ast> UnsafeASTEmitter<'a, 'tcx, 'ast> {
+    fn visit_block_post<'v>(&mut self, b: &'v hir::Block) {
+        use ::rustc::hir::BlockCheckMode::*;
+        use ::rustc::hir::UnsafeSource::UserProvided;
+        if b.expr.is_some() { self.index += 1 }
+        let unsafety = match *b {
+            hir::Block{rules: DefaultBlock, ..} => Unsafety::Normal,
+            hir::Block{rules: UnsafeBlock(UserProvided), ..} |
+            hir::Block{rules: PushUnsafeBlock(UserProvided), ..} |
+            hir::Block{rules: PopUnsafeBlock(UserProvided), ..} => Unsafety::Unsafe,
+            _ => Unsafety::Normal,
+        };
+        let (index, mut contents) = self.stack.pop().unwrap();
+        mem::swap(&mut contents, &mut self.contents);
+        let block = UASTNode::InnerBlock(
+            Box::new(Block::new(is_unsafe(unsafety), self.index, contents))
+        );
+        self.index = index;
+        self.register_point(block, b.span);
+    }
+    fn visit_fn_post<'v>(&mut self,
+                         fk: intravisit::FnKind<'v>,
+                         fd: &'v hir::FnDecl,
+                         _: &'v hir::Block,
+                         span: Span,
+                         id: ast::NodeId) {
+        use ::rustc::hir::intravisit::FnKind::{ItemFn,Method,Closure};
+        let Indexed{ item, .. } = self.contents.pop().unwrap();
+        if let UASTNode::InnerBlock(boxed_block) = item {
+            let (index, contents) = self.stack.pop().unwrap();
+            self.index = index;
+            self.contents = contents;
+            match fk {
+                ItemFn(_, generics, unsafety, _, abi, vis, _) => {
+                    let name = self.tcx.node_path_str(id);
+                    let sig = signature(fd, abi, generics);
+                    let vis = visibility(Some(vis));
+                    self.register_function(boxed_block, unsafety, name, span, sig, vis, id);
+                }
+                Method(_, method_sig, vis, _) => {
+                    let name = self.tcx.node_path_str(id);
+                    let sig = signature(fd, method_sig.abi, &method_sig.generics);
+                    let unsafety = method_sig.unsafety;
+                    let vis = visibility(vis);
+                    self.register_function(boxed_block, unsafety, name, span, sig, vis, id);
+                }
+                Closure(_) => {
+                    let closure = UASTNode::Closure(boxed_block);
+                    self.register_point(closure, span);
+                }
+            };
+        } else {
+            panic!("Found something other than a block under a fn: {:?}", item);
+        }
+    }
+}
+
+impl<'a, 'tcx: 'a, 'ast, 'v> intravisit::Visitor<'v> for UnsafeASTEmitter<'a, 'tcx, 'ast> {
+
+    fn visit_item(&mut self, i: &'v hir::Item) {
+        if self.options.module_tree {
+            self.register_container(i);
+        }
+        match i.node {
+            hir::ItemImpl(Unsafety::Unsafe, _, _, Some(ref trait_ref), ref self_ty, _) => {
+                let self_ty = hir::print::ty_to_string(self_ty);
+                self.register_unsafe_impl(trait_ref, self_ty, i.span);
+            },
+            hir::ItemDefaultImpl(Unsafety::Unsafe, ref trait_ref) => {
+                // `unsafe impl Trait for .. {}`
+                self.register_unsafe_impl(trait_ref, "..".to_string(), i.span);
+            },
+            hir::ItemTrait(Unsafety::Unsafe, _, _, _) => {
+                self.register_unsafe_trait(i.id, i.span);
+            },
+            _ => { /* No other unsafe items */ },
+        }
+        intravisit::walk_item(self, i);
+    }
+
+    fn visit_block(&mut self, b: &'v hir::Block) {
+        self.stack.push( (self.index, mem::replace(&mut self.contents, vec![])) );
+        self.index = 0;
+        intravisit::walk_block(self, b);
+        self.visit_block_post(b);
+    }
+    fn visit_stmt(&mut self, s: &'v hir::Stmt) {
+        intravisit::walk_stmt(self, s);
+        self.index += 1;
+    }
+    fn visit_expr(&mut self, expr: &'v hir::Expr) {
+        match expr.node {
+            hir::Expr_::ExprCall(ref fn_expr, _) => {
+                let fn_ty = self.tcx.expr_ty_adjusted(fn_expr);
+                let fn_safety = unsafe_from_fn_ty(fn_ty);
+                let fn_ffi = ffi_from_fn_ty(fn_ty);
+                let callee = match fn_ty.sty {
+                    ty::TyFnDef(def_id, _, _) => self.callee(def_id),
+                    _ => Callee::indirect(),
+                };
+                let unsafe_call = UASTNode::Call(fn_safety,fn_ffi,callee);
+                self.register_point(unsafe_call, expr.span);
+            },
+            hir::Expr_::ExprMethodCall(_, _, _) => {
+                let method_call = ty::MethodCall::expr(expr.id);
+                let (fn_ty, def_id) = {
+                    let tables = self.tcx.tables.borrow();
+                    let method = &tables.method_map[&method_call];
+                    (method.ty, method.def_id)
+                };
+                let fn_safety = unsafe_from_fn_ty(fn_ty);
+                let fn_ffi = ffi_from_fn_ty(fn_ty);
+                let callee = self.callee(def_id);
+                let unsafe_call = UASTNode::Call(fn_safety,fn_ffi,callee);
+                self.register_point(unsafe_call, expr.span);
+            },
+            hir::Expr_::ExprUnary(hir::UnOp::UnDeref, ref sub_expr) => {
+                let tys = self.tcx.node_id_to_type(sub_expr.id);
+                if let ty::TyRawPtr(_) = tys.sty {
+                    self.register_point(UASTNode::Deref, expr.span);
+                }
+            },
+            hir::ExprAssign(ref lhs, _) => {
+                if let Some(field) = self.union_field_written(lhs) {
+                    self.register_point(UASTNode::UnionFieldWrite, expr.span);
+                    self.union_write = Some(field.id);
+                }
+            },
+            hir::ExprAssignOp(_, ref lhs, _) => {
+                // `u.f += 1` writes the field, and also reads it, so the field expression is left
+                // to be registered as a read.
+                if self.union_field_written(lhs).is_some() {
+                    self.register_point(UASTNode::UnionFieldWrite, expr.span);
+                }
+            },
+            hir::ExprField(ref base, _) => {
+                if self.union_write == Some(expr.id) {
+                    self.union_write = None;
+                } else if is_union_ty(self.tcx.expr_ty_adjusted(base)) {
+                    self.register_point(UASTNode::UnionFieldRead, expr.span);
+                }
+            },
+            hir::ExprInlineAsm(..) => {
+                self.register_point(UASTNode::InlineASM, expr.span);
+            },
+            hir::ExprPath(..) => {
+                if let Def::Static(def_id, mutbl) = self.tcx.expect_def(expr.id) {
+                    if self.is_foreign_item(def_id) {
+                        self.register_point(UASTNode::ExternStatic, expr.span);
+                    } else if mutbl {
+                        self.register_point(UASTNode::MutStatic, expr.span);
+                    }
+                }
+            },
+            _ => { /* No other unsafe operations */ },
+        }
+        intravisit::walk_expr(self, expr);
+    }
+    fn visit_fn(&mut self,
+                fk: intravisit::FnKind<'v>,
+                fd: &'v hir::FnDecl,
+                b: &'v hir::Block,
+                s: Span,
+                id: ast::NodeId) {
+        self.stack.push( (self.index, mem::replace(&mut self.contents, vec![])) );
+        self.index = 0;
+        self.visit_block(b);
+        self.visit_fn_post(fk, fd, b, s, id);
+    }
+}
+
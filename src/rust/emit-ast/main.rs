@@ -8,23 +8,31 @@ extern crate syntax;
 extern crate rustc_driver;
 extern crate rustc_serialize;
 extern crate rustc_data_structures;
+extern crate unsafe_ast;
 
-mod unsafe_ast;
+mod emitter;
+mod options;
 
-use rustc_serialize::json;
+use rustc_serialize::json::{self,Json};
+
+use unsafe_ast::{atomic,encoding,schema};
 
 use rustc::hir;
+use rustc::middle::privacy::AccessLevels;
 use rustc::ty;
 use rustc::session::{config,Session};
 
 use rustc_driver::{driver,CompilerCalls,RustcDefaultCalls,Compilation};
 use rustc_driver::driver::CompileState;
 
+use syntax::ast;
 use syntax::diagnostics;
 
-use std::io::Write;
+use std::fs;
+use std::io::{self,Write};
 use std::mem;
-use std::path::PathBuf;
+use std::path::{Path,PathBuf};
+use std::time::{SystemTime,UNIX_EPOCH};
 
 macro_rules! errln(
     ($($arg:tt)*) => { {
@@ -33,18 +41,102 @@ macro_rules! errln(
     } }
 );
 
+/// The kind of cargo target being compiled: "lib", "bin", "test", "bench" or "example".
+fn target_kind(session: &Session) -> &'static str {
+    let in_dir = |dir: &str| {
+        session.local_crate_source_file.as_ref()
+            .map_or(false, |file| file.components().any(|c| c.as_os_str() == dir))
+    };
+    if session.opts.test {
+        if in_dir("benches") { "bench" } else { "test" }
+    } else if in_dir("examples") {
+        "example"
+    } else if session.opts.crate_types.contains(&config::CrateTypeExecutable) {
+        "bin"
+    } else {
+        "lib"
+    }
+}
+
+/// Describes the crate being compiled, and this compilation.
+fn metadata(session: &Session, options: &options::Options) -> unsafe_ast::Metadata {
+    let package = std::env::var("CARGO_PKG_NAME").ok();
+    let version = std::env::var("CARGO_PKG_VERSION").ok();
+    let package_and_version = match (&package, &version) {
+        (&Some(ref package), &Some(ref version)) => Some(format!("{}:{}", package, version)),
+        _ => None,
+    };
+    // Cargo enables features by passing `--cfg feature="..."`
+    let features = session.opts.cfg.iter().filter_map(|item| match item.node {
+        ast::MetaItemKind::NameValue(ref name, ref lit) if *name == "feature" => match lit.node {
+            ast::LitKind::Str(ref value, _) => Some(value.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }).collect();
+    let compiler_version = match rustc_driver::commit_hash_str() {
+        Some(hash) => format!("{} ({})", rustc_driver::release_str().unwrap_or("unknown"), hash),
+        None => rustc_driver::release_str().unwrap_or("unknown").to_string(),
+    };
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    unsafe_ast::Metadata::new(package_and_version,
+                              version,
+                              target_kind(session).to_string(),
+                              features,
+                              compiler_version,
+                              timestamp,
+                              options.snippet_length.map(|length| length as u64))
+}
+
+/// Writes `json` to the file `name` in `dir`, so that readers never see a partially written UAST.
+fn write_to_dir(dir: &Path, name: &str, json: &str) -> io::Result<()> {
+    try!(fs::create_dir_all(dir));
+    atomic::write_atomically(&dir.join(name), |out| writeln!(out, "{}", json))
+}
+
 fn emit_unsafe_ast<'a,'tcx,'ast>(crate_name: String,
                                  crate_type: String,
                                  krate: &hir::Crate,
                                  session: &'ast Session,
+                                 access_levels: &'ast AccessLevels,
                                  tcx: ty::TyCtxt<'a,'tcx,'tcx>) {
     // Hack to prevent dumping results from dependency builds.
     // Cargo calls these "build_script_build" for some reason.
     if crate_name != "build_script_build" {
-        let mut v = unsafe_ast::UnsafeASTEmitter::new(tcx, session, crate_name, crate_type);
+        let options = options::Options::from_env().unwrap_or_else(|e| session.fatal(&e));
+        let output = options.output.clone();
+        let encoding = options.encoding;
+        // The package, if cargo is doing the build, helps tell apart crates with the same name.
+        let file_name = match (std::env::var("CARGO_PKG_NAME"),
+                               std::env::var("CARGO_PKG_VERSION")) {
+            (Ok(pkg), Ok(version)) =>
+                format!("{}-{}.{}.{}.json", pkg, version, crate_name, target_kind(session)),
+            _ => format!("{}.{}.json", crate_name, target_kind(session)),
+        };
+        let meta = metadata(session, &options);
+        let mut v = emitter::UnsafeASTEmitter::new(tcx,
+                                                   session,
+                                                   access_levels,
+                                                   crate_name,
+                                                   crate_type,
+                                                   options);
         krate.visit_all_items(&mut v);
-        let uast = v.into_uast();
-        errln!("{}", json::as_json(&uast));
+        let uast = v.into_uast(meta);
+        let mut json = json::encode(&uast).expect("The UAST should encode as JSON");
+        if encoding == options::Encoding::Tagged {
+            let default = Json::from_str(&json).expect("The encoded UAST should be valid JSON");
+            json = encoding::tag(default).to_string();
+        }
+        match output {
+            options::Output::Stderr => errln!("{}", json),
+            options::Output::Stdout => println!("{}", json),
+            options::Output::Dir(ref dir) => {
+                if let Err(e) = write_to_dir(dir, &file_name, &json) {
+                    session.fatal(&format!("Could not write the UAST to {}: {}",
+                                           dir.join(&file_name).display(), e));
+                }
+            },
+        }
     }
 }
 
@@ -70,10 +162,16 @@ impl<'a> AnalyzeUnsafe<'a> {
             let krate = state.hir_crate.expect("HIR should exist");
             let tcx = state.tcx.expect("Type context should exist");
             let session = state.session;
+            let analysis = state.analysis.expect("Analysis should exist");
             let crate_name = state.crate_name.unwrap_or("????");
             let crate_type = state.session.opts.crate_types.iter()
                 .next().map(|t| format!("{:?}",t)).unwrap_or("????".to_string());
-            emit_unsafe_ast(crate_name.to_string(), crate_type, krate, session, tcx);
+            emit_unsafe_ast(crate_name.to_string(),
+                            crate_type,
+                            krate,
+                            session,
+                            &analysis.access_levels,
+                            tcx);
         }))
     }
 }
@@ -138,6 +236,11 @@ impl<'a,'callback: 'a> CompilerCalls<'a> for AnalyzeUnsafe<'callback> {
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
+    // Handled here, rather than passed on to rustc
+    if args.get(1).map_or(false, |arg| arg == "--uast-schema") {
+        println!("{}", schema::schema().pretty());
+        return;
+    }
     let mut analyzer = AnalyzeUnsafe::unsafe_ast_emitter();
     rustc_driver::run_compiler(&args, &mut analyzer);
 }
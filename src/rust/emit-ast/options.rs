@@ -0,0 +1,97 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Settings for the Unsafe AST emitter
+//
+// `emit-ast` is run by cargo in place of `rustc`, so there is no good way to hand it extra
+// command line arguments. Instead, it is configured through `UAST_*` environment variables.
+
+use std::env;
+use std::path::PathBuf;
+
+/// Set to record the module tree, and how functions are grouped into impls, in `Crate`.
+pub const MODULE_TREE_VAR: &'static str = "UAST_MODULE_TREE";
+
+/// Set to record a `SpanInfo` (file, lines, columns, byte offsets) alongside span strings.
+pub const STRUCTURED_SPANS_VAR: &'static str = "UAST_STRUCTURED_SPANS";
+
+/// The most characters of source to include in each snippet: a number, "unlimited", or "none".
+pub const SNIPPET_LENGTH_VAR: &'static str = "UAST_SNIPPET_LENGTH";
+
+const DEFAULT_SNIPPET_LENGTH: usize = 40;
+
+/// Where to write each crate's UAST: "stderr" (the default), "stdout", or a directory.
+pub const OUTPUT_VAR: &'static str = "UAST_OUTPUT";
+
+/// How to encode UAST nodes: "default", or "tagged" (see `encoding.rs`).
+pub const ENCODING_VAR: &'static str = "UAST_ENCODING";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Default,
+    Tagged,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Output {
+    Stderr,
+    Stdout,
+    /// Write a separate file for each crate and target into this directory
+    Dir(PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub module_tree: bool,
+    pub structured_spans: bool,
+    /// `None` if snippets should not be truncated
+    pub snippet_length: Option<usize>,
+    pub output: Output,
+    pub encoding: Encoding,
+}
+
+impl Options {
+    pub fn from_env() -> Result<Options, String> {
+        Ok(Options {
+            module_tree: env_flag(MODULE_TREE_VAR),
+            structured_spans: env_flag(STRUCTURED_SPANS_VAR),
+            snippet_length: try!(snippet_length()),
+            output: output(),
+            encoding: try!(encoding()),
+        })
+    }
+}
+
+fn encoding() -> Result<Encoding, String> {
+    match env::var(ENCODING_VAR) {
+        Err(_) => Ok(Encoding::Default),
+        Ok(ref v) if v == "default" => Ok(Encoding::Default),
+        Ok(ref v) if v == "tagged" => Ok(Encoding::Tagged),
+        Ok(v) => Err(format!("{} should be \"default\" or \"tagged\", not \"{}\"",
+                             ENCODING_VAR, v)),
+    }
+}
+
+fn output() -> Output {
+    match env::var_os(OUTPUT_VAR) {
+        None => Output::Stderr,
+        Some(ref v) if v == "stderr" => Output::Stderr,
+        Some(ref v) if v == "stdout" => Output::Stdout,
+        Some(v) => Output::Dir(PathBuf::from(v)),
+    }
+}
+
+fn snippet_length() -> Result<Option<usize>, String> {
+    match env::var(SNIPPET_LENGTH_VAR) {
+        Err(_) => Ok(Some(DEFAULT_SNIPPET_LENGTH)),
+        Ok(ref v) if v == "unlimited" => Ok(None),
+        Ok(ref v) if v == "none" => Ok(Some(0)),
+        Ok(v) => v.parse().map(Some).map_err(|_| {
+            format!("{} should be a number, \"unlimited\", or \"none\", not \"{}\"",
+                    SNIPPET_LENGTH_VAR, v)
+        }),
+    }
+}
+
+/// A flag is set if its variable is present, and not empty or "0".
+fn env_flag(var: &str) -> bool {
+    env::var(var).map(|v| !v.is_empty() && v != "0").unwrap_or(false)
+}
@@ -0,0 +1,279 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// The analyses of `data-analysis/unsafe.jq`, ported to Rust
+//
+// Each function here computes the same thing as the jq function of the same name, and produces
+// things in the same order, so that `analyze` can stand in for jq over a whole `json.out`. Where
+// the jq functions have quirks (`used_indices` counting safe calls, say), the ports keep them.
+
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{self,Json};
+
+use jq;
+use uast::{Block,Crate,Indexed,UASTNode};
+
+/// The blocks in `block`, itself included, in the order of jq's `..`: each block comes before the
+/// blocks nested in it, which come in statement order.
+pub fn blocks(block: &Block) -> Vec<&Block> {
+    let mut blocks = vec![block];
+    for node in block.contents() {
+        if let Some(inner) = node.item().block() {
+            blocks.extend(self::blocks(inner));
+        }
+    }
+    blocks
+}
+
+/// The blocks of the closures in `block`, in the same order as `blocks`.
+pub fn closure_blocks(block: &Block) -> Vec<&Block> {
+    let mut closures = vec![];
+    for node in block.contents() {
+        if let UASTNode::Closure(ref inner) = *node.item() {
+            closures.push(&**inner);
+        }
+        if let Some(inner) = node.item().block() {
+            closures.extend(closure_blocks(inner));
+        }
+    }
+    closures
+}
+
+/// The sum of the sizes of `block` and all the blocks and closures nested in it.
+pub fn block_net_size(block: &Block) -> u64 {
+    blocks(block).iter().map(|b| b.size()).sum()
+}
+
+/// The number of statements in `block` and the blocks and closures nested in it, counting those
+/// statements which consist of nested blocks only once.
+pub fn block_net_size_2(block: &Block) -> i64 {
+    let children: i64 = block.children().into_iter().map(block_net_size_2).sum();
+    let mut indices: Vec<u64> = block.contents().iter()
+        .filter(|node| node.item().block().is_some())
+        .map(|node| node.index())
+        .collect();
+    indices.sort();
+    indices.dedup();
+    block.size() as i64 + children - indices.len() as i64
+}
+
+/// The indices of the statements (and final expression) in `block` which require unsafe, sorted.
+/// A nested safe block or closure requires unsafe if it has any uses of its own.
+pub fn used_indices(block: &Block) -> Vec<u64> {
+    let mut indices: Vec<u64> = block.contents().iter()
+        .filter(|node| match *node.item() {
+            UASTNode::InnerBlock(ref inner) => !inner.unsaf() && !inner.own_uses().is_empty(),
+            UASTNode::Closure(ref inner) => !inner.own_uses().is_empty(),
+            ref item => item.is_unsafe_use(),
+        })
+        .map(|node| node.index())
+        .collect();
+    indices.sort();
+    indices.dedup();
+    indices
+}
+
+/// What fraction of the statements in `block` require unsafe.
+pub fn block_requirement(block: &Block) -> f64 {
+    if block.size() > 0 {
+        used_indices(block).len() as f64 / block.size() as f64
+    } else {
+        0.0
+    }
+}
+
+/// The unsafe blocks directly inside each block of each function: for each block (in the order of
+/// `blocks`), its unsafe inner blocks, in statement order.
+pub fn unsafe_blocks(krate: &Crate) -> Vec<&Block> {
+    let mut unsafe_blocks = vec![];
+    for function in krate.functions() {
+        for block in blocks(function.block()) {
+            unsafe_blocks.extend(block.contents().iter().filter_map(|node| match *node.item() {
+                UASTNode::InnerBlock(ref inner) if inner.unsaf() => Some(&**inner),
+                _ => None,
+            }));
+        }
+    }
+    unsafe_blocks
+}
+
+/// Each unsafe block, with the body of the function it is in, as `(parent, child)`.
+pub fn unsafe_blocks_with_functions(krate: &Crate) -> Vec<(&Block, &Block)> {
+    let mut pairs = vec![];
+    for function in krate.functions() {
+        for block in blocks(function.block()) {
+            if block.unsaf() {
+                pairs.push((function.block(), block));
+            }
+        }
+    }
+    pairs
+}
+
+/// The net size of `child` relative to that of `parent`.
+pub fn block_relative_size(parent: &Block, child: &Block) -> f64 {
+    let child_size = block_net_size_2(child);
+    let parent_size = block_net_size_2(parent);
+    if parent_size > 0 { child_size as f64 / parent_size as f64 } else { 1.0 }
+}
+
+/// The mean of `xs`, summing them in order as jq does.
+pub fn mean(xs: &[f64]) -> f64 {
+    xs.iter().fold(0.0, |sum, x| sum + x) / xs.len() as f64
+}
+
+/// The relative size of each unsafe block in the crate.
+pub fn unsafe_blocks_rel_sizes(krate: &Crate) -> Vec<f64> {
+    unsafe_blocks_with_functions(krate).into_iter()
+        .map(|(parent, child)| block_relative_size(parent, child))
+        .collect()
+}
+
+/// The block requirement of each unsafe block in the crate, nested ones included.
+pub fn unsafe_blocks_requirement(krate: &Crate) -> Vec<f64> {
+    unsafe_blocks_with_functions(krate).into_iter()
+        .map(|(_, child)| block_requirement(child))
+        .collect()
+}
+
+/// All the unsafe uses in the crate, function by function.
+pub fn all_unsafe_uses(krate: &Crate) -> Vec<&Indexed<UASTNode>> {
+    krate.functions().iter()
+        .flat_map(|function| function.block().all_uses())
+        .filter(|u| u.item().is_unsafe_use())
+        .collect()
+}
+
+/// The blocks of the closures in the crate which have unsafe uses of their own.
+pub fn closure_blocks_with_unsafe_uses(krate: &Crate) -> Vec<&Block> {
+    let mut closures = vec![];
+    for function in krate.functions() {
+        closures.extend(closure_blocks(function.block()).into_iter()
+            .filter(|closure| closure.own_uses().iter().any(|u| u.item().is_unsafe_use())));
+    }
+    closures
+}
+
+/// The unsafe uses belonging to each unsafe block (see `unsafe_blocks`) and then to each unsafe
+/// function.
+pub fn all_unsafe_uses_in_unsafe_declarations(krate: &Crate) -> Vec<&Indexed<UASTNode>> {
+    let mut declarations = unsafe_blocks(krate);
+    declarations.extend(krate.functions().iter().filter(|f| f.unsaf()).map(|f| f.block()));
+    declarations.into_iter()
+        .flat_map(|block| block.own_uses())
+        .filter(|u| u.item().is_unsafe_use())
+        .collect()
+}
+
+/// An analysis which can be run from the command line, producing one line of output (as
+/// `jq -c` would) for each line of input.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Analysis {
+    BlockNetSize,
+    BlockNetSize2,
+    UsedIndices,
+    BlockRequirement,
+    UnsafeBlocksRelSizes,
+    ClosureBlocksWithUnsafeUses,
+    AllUnsafeUsesInUnsafeDeclarations,
+}
+
+pub const ANALYSES: &'static [Analysis] = &[
+    Analysis::BlockNetSize,
+    Analysis::BlockNetSize2,
+    Analysis::UsedIndices,
+    Analysis::BlockRequirement,
+    Analysis::UnsafeBlocksRelSizes,
+    Analysis::ClosureBlocksWithUnsafeUses,
+    Analysis::AllUnsafeUsesInUnsafeDeclarations,
+];
+
+impl Analysis {
+    /// The name of the jq function the analysis is a port of.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Analysis::BlockNetSize => "block_net_size",
+            Analysis::BlockNetSize2 => "block_net_size_2",
+            Analysis::UsedIndices => "used_indices",
+            Analysis::BlockRequirement => "block_requirement",
+            Analysis::UnsafeBlocksRelSizes => "unsafe_blocks_rel_sizes",
+            Analysis::ClosureBlocksWithUnsafeUses => "closure_blocks_with_unsafe_uses",
+            Analysis::AllUnsafeUsesInUnsafeDeclarations =>
+                "all_unsafe_uses_in_unsafe_declarations",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Analysis> {
+        ANALYSES.iter().find(|analysis| analysis.name() == name).cloned()
+    }
+
+    /// Whether the analysis takes blocks, rather than whole UASTs.
+    pub fn takes_block(&self) -> bool {
+        match *self {
+            Analysis::BlockNetSize |
+            Analysis::BlockNetSize2 |
+            Analysis::UsedIndices |
+            Analysis::BlockRequirement => true,
+            _ => false,
+        }
+    }
+
+    /// Runs the analysis on one line of input: a block, or an encoded UAST, as `takes_block`
+    /// says.
+    pub fn run(&self, line: &str) -> json::DecodeResult<String> {
+        if self.takes_block() {
+            let block = try!(decode_block(line));
+            Ok(self.run_on_block(&block))
+        } else {
+            // Parts of the UAST are printed as they were read, as jq prints them
+            let read = try!(jq::parse(line).map_err(json::DecoderError::ParseError));
+            let krate = try!(::decode(line));
+            Ok(self.run_on_crate(&krate, &jq::Printer::as_read(&krate, &read)))
+        }
+    }
+
+    fn run_on_block(&self, block: &Block) -> String {
+        match *self {
+            Analysis::BlockNetSize => jq::number(block_net_size(block) as f64),
+            Analysis::BlockNetSize2 => jq::number(block_net_size_2(block) as f64),
+            Analysis::UsedIndices => {
+                let indices: Vec<String> = used_indices(block).into_iter()
+                    .map(|index| jq::number(index as f64))
+                    .collect();
+                jq::array(&indices)
+            },
+            Analysis::BlockRequirement => jq::number(block_requirement(block)),
+            _ => panic!("{} does not take a block", self.name()),
+        }
+    }
+
+    fn run_on_crate(&self, krate: &Crate, printer: &jq::Printer) -> String {
+        let name = jq::string(krate.name());
+        match *self {
+            Analysis::UnsafeBlocksRelSizes => {
+                let sizes: Vec<String> = unsafe_blocks_rel_sizes(krate).into_iter()
+                    .map(jq::number)
+                    .collect();
+                jq::object(&[("name", name), ("rel_sizes", jq::array(&sizes))])
+            },
+            Analysis::ClosureBlocksWithUnsafeUses => {
+                let closures: Vec<String> = closure_blocks_with_unsafe_uses(krate).into_iter()
+                    .map(|closure| printer.block(closure))
+                    .collect();
+                jq::object(&[("name", name), ("closures", jq::array(&closures))])
+            },
+            Analysis::AllUnsafeUsesInUnsafeDeclarations => {
+                let uses: Vec<String> = all_unsafe_uses_in_unsafe_declarations(krate).into_iter()
+                    .map(|node| printer.node(node))
+                    .collect();
+                jq::object(&[("name", name), ("uses", jq::array(&uses))])
+            },
+            _ => panic!("{} does not take a UAST", self.name()),
+        }
+    }
+}
+
+/// Decodes a block, in either encoding, upgrading it if it is from before the UAST was versioned.
+fn decode_block(line: &str) -> json::DecodeResult<Block> {
+    let json = try!(Json::from_str(line).map_err(json::DecoderError::ParseError));
+    let json = ::encoding::upgrade_block(::encoding::untag(json));
+    Decodable::decode(&mut json::Decoder::new(json))
+}
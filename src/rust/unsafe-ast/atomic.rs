@@ -0,0 +1,79 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Writing files so that readers never see them half-written
+//
+// A file is written to a temporary file beside it, `.<name>.tmp`, which is moved into place once
+// it is complete. If writing fails, the temporary file is removed, and whatever was at the path
+// before is left alone.
+
+use std::ffi::OsString;
+use std::fs::{self,File};
+use std::io::{self,BufWriter,Write};
+use std::path::{Path,PathBuf};
+
+/// A file being written to a temporary path, which `commit` moves into place. It is removed if it
+/// is dropped uncommitted.
+pub struct AtomicFile {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    out: Option<BufWriter<File>>,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> io::Result<AtomicFile> {
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(path.file_name().unwrap_or(path.as_os_str()));
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+        let out = BufWriter::new(try!(File::create(&tmp_path)));
+        Ok(AtomicFile {
+            path: path.to_path_buf(),
+            tmp_path: tmp_path,
+            out: Some(out),
+            committed: false,
+        })
+    }
+
+    /// Where the file goes.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Moves the file into place, once everything written to it is on disk.
+    pub fn commit(mut self) -> io::Result<()> {
+        let out = self.out.take().expect("Only commit takes the file");
+        let file = try!(out.into_inner().map_err(io::Error::from));
+        try!(file.sync_all());
+        try!(fs::rename(&self.tmp_path, &self.path));
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.as_mut().expect("Only commit takes the file").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.as_mut().expect("Only commit takes the file").flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        // Closed first, so that it can be removed everywhere
+        self.out.take();
+        if !self.committed {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+/// Writes the file at `path` with `write`, moving it into place only if `write` succeeds.
+pub fn write_atomically<F>(path: &Path, write: F) -> io::Result<()>
+    where F: FnOnce(&mut AtomicFile) -> io::Result<()> {
+    let mut file = try!(AtomicFile::create(path));
+    try!(write(&mut file));
+    file.commit()
+}
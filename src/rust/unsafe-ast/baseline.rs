@@ -0,0 +1,344 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Baselines of a crate's unsafe sites, for failing CI when unreviewed unsafe code is added
+//
+// The unsafe sites of a crate are its unsafe functions, its unsafe blocks and its unsafe uses. A
+// site is identified by its fingerprint: an FNV-1a hash of the crate name, the kind of site, the
+// path of the function it is in, its statement (the indices of the statements it is in, outermost
+// first, like "2.0") and its snippet (with runs of whitespace collapsed). So moving a function
+// around, or reformatting it, doesn't make its sites new, but adding or removing statements before
+// a site in its block does. The statement tells apart sites whose snippets are the same, like two
+// `*p`s, or two long calls which are only the same up to where their snippets were cut short.
+// Sites with the same fingerprint (two `*p`s in one statement, say) are counted.
+//
+// Since snippets are cut to `UAST_SNIPPET_LENGTH` characters, the same code has different
+// fingerprints under different snippet lengths. A baseline records the snippet length of the UASTs
+// it was made from (`meta.snippet_length`), and refuses to check UASTs with another.
+//
+// A baseline is a text file with one site per line, which people can read, review and annotate:
+//
+//     # unsafe-ast baseline 2 snippet_length=<length, or "unlimited">
+//     <fingerprint>	<crate>	<kind>	<function>	<statement>	<snippet>	<review note>
+//
+// A site with a review note has been reviewed. Rewriting a baseline keeps the notes of the sites
+// which are still there.
+
+use std::collections::HashMap;
+use std::io::{self,BufRead,Write};
+
+use uast::{Crate,FnDecl,Indexed,MacroOrigin,SpanInfo,UASTNode};
+use visit::{self,Context,UastVisitor};
+
+const HEADER: &'static str = "# unsafe-ast baseline 2";
+/// What follows `HEADER` in the header, before the snippet length.
+const SNIPPET_LENGTH: &'static str = " snippet_length=";
+
+/// The 64 bit FNV-1a hash of `bytes`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `snippet`, with each run of whitespace made a single space.
+pub fn normalize_snippet(snippet: &str) -> String {
+    snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The snippet length of the UASTs `crates`, which must all have the same one (see
+/// `Metadata::snippet_length`).
+pub fn snippet_length(crates: &[Crate]) -> Result<Option<u64>, String> {
+    let mut lengths = crates.iter().map(|krate| krate.meta().snippet_length());
+    let length = lengths.next().unwrap_or(None);
+    if lengths.any(|other| other != length) {
+        return Err("The UASTs were emitted with different snippet lengths \
+                    (UAST_SNIPPET_LENGTH)".to_string());
+    }
+    Ok(length)
+}
+
+/// The snippet length in the header (the first line) of a baseline.
+fn read_header(line: &str) -> Result<Option<u64>, String> {
+    if !line.starts_with("# unsafe-ast baseline ") {
+        return Err("Not an unsafe-ast baseline".to_string());
+    }
+    let setting = if line.starts_with(HEADER) { line[HEADER.len()..].trim_right() } else { "" };
+    if !setting.starts_with(SNIPPET_LENGTH) {
+        return Err("The baseline is from another version of unsafe-ast, and has to be made afresh"
+                       .to_string());
+    }
+    match &setting[SNIPPET_LENGTH.len()..] {
+        "unlimited" => Ok(None),
+        length => length.parse().map(Some)
+            .map_err(|_| format!("The baseline has a bad snippet length: {}", length)),
+    }
+}
+
+fn describe_snippet_length(snippet_length: Option<u64>) -> String {
+    match snippet_length {
+        Some(length) => format!("snippets of at most {} characters", length),
+        None => "whole snippets".to_string(),
+    }
+}
+
+/// An unsafe function, unsafe block or unsafe use.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Site {
+    krate: String,
+    kind: String,
+    function: String,
+    statement: String,
+    snippet: String,
+    span: String,
+    span_info: Option<SpanInfo>,
+    macro_origin: MacroOrigin,
+}
+
+impl Site {
+    fn new(krate: &Crate, kind: &str, function: &str, statement: &[u64], snippet: &str,
+           span: &str, span_info: Option<&SpanInfo>, macro_origin: MacroOrigin) -> Site {
+        let statement: Vec<String> = statement.iter().map(|index| index.to_string()).collect();
+        Site {
+            krate: krate.name().to_string(),
+            kind: kind.to_string(),
+            function: function.to_string(),
+            statement: statement.join("."),
+            snippet: normalize_snippet(snippet),
+            span: span.to_string(),
+            span_info: span_info.cloned(),
+            macro_origin: macro_origin,
+        }
+    }
+
+    fn of_node(krate: &Crate, function: &str, statement: &[u64], node: &Indexed<UASTNode>)
+               -> Site {
+        let kind = match *node.item() {
+            UASTNode::InnerBlock(_) => "UnsafeBlock",
+            ref item => item.kind(),
+        };
+        Site::new(krate, kind, function, statement, node.snippet(), node.span(),
+                  node.span_info(), node.macro_origin())
+    }
+
+    pub fn krate(&self) -> &str { &self.krate }
+    /// "UnsafeFn", "UnsafeBlock", or the kind of use (see `UASTNode::kind`).
+    pub fn kind(&self) -> &str { &self.kind }
+    pub fn function(&self) -> &str { &self.function }
+    /// The indices of the statements the site is in, like "2.0", which is empty for unsafe
+    /// functions.
+    pub fn statement(&self) -> &str { &self.statement }
+    /// The normalized snippet, which is empty for unsafe functions.
+    pub fn snippet(&self) -> &str { &self.snippet }
+    pub fn span(&self) -> &str { &self.span }
+    pub fn span_info(&self) -> Option<&SpanInfo> { self.span_info.as_ref() }
+    pub fn macro_origin(&self) -> MacroOrigin { self.macro_origin }
+
+    pub fn fingerprint(&self) -> u64 {
+        let key = [&self.krate[..], &self.kind, &self.function, &self.statement, &self.snippet]
+            .join("\0");
+        fnv1a(key.as_bytes())
+    }
+}
+
+/// The unsafe sites of a crate, function by function.
+pub fn sites(krate: &Crate) -> Vec<Site> {
+    let mut sites = vec![];
+    for function in krate.functions() {
+        if function.unsaf() {
+            sites.push(Site::new(krate, "UnsafeFn", function.name(), &[], "", function.span(),
+                                 function.span_info(), function.macro_origin()));
+        }
+        for (statement, node) in node_sites(function) {
+            sites.push(Site::of_node(krate, function.name(), &statement, node));
+        }
+    }
+    sites
+}
+
+/// Like `visit::unsafe_sites`, with the statement of each site.
+fn node_sites(function: &FnDecl) -> Vec<(Vec<u64>, &Indexed<UASTNode>)> {
+    struct Sites<'a> {
+        statement: Vec<u64>,
+        sites: Vec<(Vec<u64>, &'a Indexed<UASTNode>)>,
+    }
+
+    impl<'a> UastVisitor<'a> for Sites<'a> {
+        fn visit_node(&mut self, node: &'a Indexed<UASTNode>, cx: &Context<'a>) {
+            self.statement.push(node.index());
+            if visit::is_unsafe_site(node) {
+                self.sites.push((self.statement.clone(), node));
+            }
+            visit::walk_node(self, node, cx);
+            self.statement.pop();
+        }
+    }
+
+    let mut sites = Sites { statement: vec![], sites: vec![] };
+    sites.visit_fn(function, &Context::new());
+    sites.sites
+}
+
+/// A site in a baseline.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Entry {
+    fingerprint: u64,
+    krate: String,
+    kind: String,
+    function: String,
+    statement: String,
+    snippet: String,
+    note: Option<String>,
+}
+
+impl Entry {
+    pub fn fingerprint(&self) -> u64 { self.fingerprint }
+    pub fn krate(&self) -> &str { &self.krate }
+    pub fn kind(&self) -> &str { &self.kind }
+    pub fn function(&self) -> &str { &self.function }
+    pub fn statement(&self) -> &str { &self.statement }
+    pub fn snippet(&self) -> &str { &self.snippet }
+    /// The review note, if the site has been reviewed.
+    pub fn note(&self) -> Option<&str> { self.note.as_ref().map(|n| &n[..]) }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Baseline {
+    snippet_length: Option<u64>,
+    entries: Vec<Entry>,
+}
+
+/// How the unsafe sites of a crate compare with a baseline.
+#[derive(Clone, Debug)]
+pub struct Check<'a> {
+    new: Vec<&'a Site>,
+    unreviewed: Vec<&'a Entry>,
+    gone: Vec<&'a Entry>,
+}
+
+impl<'a> Check<'a> {
+    /// The sites which aren't in the baseline.
+    pub fn new_sites(&self) -> &[&'a Site] { &self.new }
+    /// The sites which are in the baseline, but haven't been reviewed.
+    pub fn unreviewed(&self) -> &[&'a Entry] { &self.unreviewed }
+    /// The sites in the baseline which are no longer there.
+    pub fn gone(&self) -> &[&'a Entry] { &self.gone }
+}
+
+impl Baseline {
+    /// A baseline of `sites`, from UASTs with the snippet length `snippet_length`, keeping the
+    /// review notes they have in `previous`.
+    pub fn new(sites: &[Site], snippet_length: Option<u64>, previous: Option<&Baseline>)
+               -> Baseline {
+        let mut notes: HashMap<u64, Vec<Option<String>>> = HashMap::new();
+        if let Some(previous) = previous {
+            for entry in previous.entries.iter().rev() {
+                notes.entry(entry.fingerprint).or_insert_with(Vec::new).push(entry.note.clone());
+            }
+        }
+        let entries = sites.iter().map(|site| {
+            let fingerprint = site.fingerprint();
+            Entry {
+                fingerprint: fingerprint,
+                krate: site.krate.clone(),
+                kind: site.kind.clone(),
+                function: site.function.clone(),
+                statement: site.statement.clone(),
+                snippet: site.snippet.clone(),
+                note: notes.get_mut(&fingerprint).and_then(|notes| notes.pop()).and_then(|n| n),
+            }
+        }).collect();
+        Baseline { snippet_length: snippet_length, entries: entries }
+    }
+
+    /// The snippet length of the UASTs the baseline was made from.
+    pub fn snippet_length(&self) -> Option<u64> {
+        self.snippet_length
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn read<R: BufRead>(input: R) -> io::Result<Baseline> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut snippet_length = None;
+        let mut entries = vec![];
+        for (n, line) in input.lines().enumerate() {
+            let line = try!(line);
+            if n == 0 {
+                snippet_length = try!(read_header(&line).map_err(&invalid));
+                continue;
+            }
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(7, '\t').collect();
+            if fields.len() < 6 {
+                return Err(invalid(format!("Line {} has too few fields", n + 1)));
+            }
+            let fingerprint = try!(u64::from_str_radix(fields[0], 16).map_err(|_| {
+                invalid(format!("Line {} has a bad fingerprint: {}", n + 1, fields[0]))
+            }));
+            let note = fields.get(6).map(|note| note.trim()).unwrap_or("");
+            entries.push(Entry {
+                fingerprint: fingerprint,
+                krate: fields[1].to_string(),
+                kind: fields[2].to_string(),
+                function: fields[3].to_string(),
+                statement: fields[4].to_string(),
+                snippet: fields[5].to_string(),
+                note: if note.is_empty() { None } else { Some(note.to_string()) },
+            });
+        }
+        Ok(Baseline { snippet_length: snippet_length, entries: entries })
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(writeln!(out, "{}{}{}", HEADER, SNIPPET_LENGTH,
+                      self.snippet_length.map_or("unlimited".to_string(), |l| l.to_string())));
+        try!(writeln!(out, "# fingerprint\tcrate\tkind\tfunction\tstatement\tsnippet\t\
+                            review note"));
+        try!(writeln!(out, "# A note after the last tab of a line marks the site as reviewed."));
+        for entry in &self.entries {
+            try!(writeln!(out, "{:016x}\t{}\t{}\t{}\t{}\t{}\t{}", entry.fingerprint, entry.krate,
+                          entry.kind, entry.function, entry.statement, entry.snippet,
+                          entry.note.as_ref().map_or("", |n| &n[..])));
+        }
+        Ok(())
+    }
+
+    /// Compares `sites`, from UASTs with the snippet length `snippet_length`, with the baseline. A
+    /// site with the same fingerprint as n in the baseline is new if it is there more than n times.
+    /// Fails if the baseline was made with another snippet length, since then the fingerprints of
+    /// the same sites can differ.
+    pub fn check<'a>(&'a self, sites: &'a [Site], snippet_length: Option<u64>)
+                     -> Result<Check<'a>, String> {
+        if snippet_length != self.snippet_length {
+            return Err(format!("The baseline was made from UASTs with {}, but these have {}. Emit \
+                                them with the same UAST_SNIPPET_LENGTH, or rebuild the baseline.",
+                               describe_snippet_length(self.snippet_length),
+                               describe_snippet_length(snippet_length)));
+        }
+        let mut remaining: HashMap<u64, Vec<&Entry>> = HashMap::new();
+        for entry in self.entries.iter().rev() {
+            remaining.entry(entry.fingerprint).or_insert_with(Vec::new).push(entry);
+        }
+        let mut new = vec![];
+        let mut unreviewed = vec![];
+        for site in sites {
+            match remaining.get_mut(&site.fingerprint()).and_then(|entries| entries.pop()) {
+                Some(entry) => if entry.note.is_none() { unreviewed.push(entry) },
+                None => new.push(site),
+            }
+        }
+        // What's left in `remaining` is gone; the entries are compared by address, since a
+        // baseline may hold several equal entries
+        let gone = self.entries.iter()
+            .filter(|&entry| {
+                let address = entry as *const Entry;
+                remaining.get(&entry.fingerprint)
+                         .map_or(false, |left| left.iter().any(|&left| left as *const _ == address))
+            })
+            .collect();
+        Ok(Check { new: new, unreviewed: unreviewed, gone: gone })
+    }
+}
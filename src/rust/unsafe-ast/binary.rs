@@ -0,0 +1,673 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// A compact binary encoding of UASTs
+//
+// Most of `json.out` is the same keys (`macro_origin`, `span_info`, ..) and strings (file names,
+// callee paths) over and over. This encoding writes no keys at all, and each distinct string only
+// once per crate.
+//
+// A file is the magic bytes `UAST` and a version byte, followed by one record per crate, each of
+// which is its length and then:
+//
+//   * The string table: the number of strings, and then each string as its length in bytes and
+//     its UTF-8.
+//   * The crate, as `Encodable` walks it: struct fields in declaration order, with nothing
+//     between them, enum variants and sequence lengths as numbers, options as a 0 or 1 byte,
+//     booleans as a byte, floats as their little-endian bits, and strings as indices into the
+//     string table.
+//
+// All numbers (lengths and indices included) are LEB128 varints, with signed ones zig-zag encoded
+// first. Each record stands alone, so records can be decoded in parallel, and files concatenated.
+//
+// The encoding is only as stable as the declaration order of the data model, so `VERSION` has to
+// change whenever `uast.rs` does. Records are written from decoded `Crate`s, so old JSON records
+// come out upgraded (see `encoding::upgrade`).
+
+use rustc_serialize::{self,Decodable,Encodable};
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io::{self,Read,Write};
+use std::mem;
+use std::str;
+
+use uast::Crate;
+
+/// The start of every binary UAST file.
+pub const MAGIC: &'static [u8] = b"UAST";
+
+/// The version of the encoding.
+pub const VERSION: u8 = 1;
+
+/// Why a record (or file) could not be decoded.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The input ended in the middle of something.
+    UnexpectedEnd,
+    /// A number was too large for the type being read.
+    Overflow,
+    /// A string which isn't UTF-8, or a character which isn't a Unicode scalar value.
+    BadText,
+    /// A string index past the end of the string table.
+    BadString(u64),
+    /// Bytes were left over after the crate.
+    TrailingBytes(usize),
+    /// The file doesn't start with `MAGIC`.
+    NotBinary,
+    /// The file is from another version of the encoding.
+    BadVersion(u8),
+    Io(io::Error),
+    /// The data didn't fit the data model, as the decoder of some type reported.
+    Invalid(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::UnexpectedEnd => write!(f, "Unexpected end of record"),
+            DecodeError::Overflow => write!(f, "Number out of range"),
+            DecodeError::BadText => write!(f, "Invalid string or character"),
+            DecodeError::BadString(i) => write!(f, "String index {} is past the string table", i),
+            DecodeError::TrailingBytes(n) => write!(f, "{} bytes left over after the crate", n),
+            DecodeError::NotBinary => write!(f, "Not a binary UAST file"),
+            DecodeError::BadVersion(v) => {
+                write!(f, "Binary UAST version {}, but this reads version {}", v, VERSION)
+            },
+            DecodeError::Io(ref e) => write!(f, "{}", e),
+            DecodeError::Invalid(ref msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for DecodeError {
+    fn description(&self) -> &str {
+        "invalid binary UAST"
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(e: io::Error) -> DecodeError {
+        DecodeError::Io(e)
+    }
+}
+
+pub type DecodeResult<T> = Result<T, DecodeError>;
+
+/// Encoding can't fail, since it only writes to memory.
+#[derive(Debug)]
+pub enum EncodeError {}
+
+/// Encodes `krate` as a record, without its length.
+pub fn encode(krate: &Crate) -> Vec<u8> {
+    let mut encoder = Encoder::new();
+    match krate.encode(&mut encoder) {
+        Ok(()) => {},
+        Err(e) => match e {},
+    }
+    encoder.into_record()
+}
+
+/// Decodes a record, without its length.
+pub fn decode(record: &[u8]) -> DecodeResult<Crate> {
+    let mut decoder = try!(Decoder::new(record));
+    let krate = try!(Crate::decode(&mut decoder));
+    match decoder.remaining() {
+        0 => Ok(krate),
+        n => Err(DecodeError::TrailingBytes(n)),
+    }
+}
+
+/// Whether `prefix`, the start of a file, is the start of a binary UAST file, rather than of JSON.
+pub fn is_binary(prefix: &[u8]) -> bool {
+    prefix.starts_with(MAGIC)
+}
+
+pub fn write_header<W: Write>(output: &mut W) -> io::Result<()> {
+    try!(output.write_all(MAGIC));
+    output.write_all(&[VERSION])
+}
+
+/// Writes a record (as made by `encode`), with its length.
+pub fn write_record<W: Write>(output: &mut W, record: &[u8]) -> io::Result<()> {
+    let mut length = vec![];
+    write_varint(&mut length, record.len() as u64);
+    try!(output.write_all(&length));
+    output.write_all(record)
+}
+
+/// Reads the header of a binary UAST file, and then its records, one at a time.
+pub fn records<R: Read>(mut input: R) -> DecodeResult<Records<R>> {
+    let mut header = [0; 5];
+    try!(read_exactly(&mut input, &mut header));
+    if !is_binary(&header) {
+        return Err(DecodeError::NotBinary);
+    }
+    if header[4] != VERSION {
+        return Err(DecodeError::BadVersion(header[4]));
+    }
+    Ok(Records { input: input, offset: header.len() as u64 })
+}
+
+/// Reads a record (written by `write_record`) without its length, or `None` at the end of the
+/// input.
+pub fn read_record<R: Read>(input: &mut R) -> DecodeResult<Option<Vec<u8>>> {
+    // The length, or the end of the input
+    let mut length = 0u64;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0];
+        if try!(input.read(&mut byte)) == 0 {
+            return if shift == 0 { Ok(None) } else { Err(DecodeError::UnexpectedEnd) };
+        }
+        if shift >= 64 {
+            return Err(DecodeError::Overflow);
+        }
+        length |= ((byte[0] & 0x7f) as u64) << shift;
+        shift += 7;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut record = vec![];
+    try!(input.take(length).read_to_end(&mut record));
+    if (record.len() as u64) < length {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    Ok(Some(record))
+}
+
+/// The records of a binary UAST file, without their lengths, ready for `decode`.
+pub struct Records<R> {
+    input: R,
+    offset: u64,
+}
+
+impl<R> Records<R> {
+    /// Where the next record starts, in bytes from the start of the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl<R: Read> Iterator for Records<R> {
+    type Item = DecodeResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<DecodeResult<Vec<u8>>> {
+        match read_record(&mut self.input) {
+            Ok(Some(record)) => {
+                self.offset += record_length(&record);
+                Some(Ok(record))
+            },
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// The number of bytes `write_record` writes for `record`.
+pub fn record_length(record: &[u8]) -> u64 {
+    let mut length = vec![];
+    write_varint(&mut length, record.len() as u64);
+    (length.len() + record.len()) as u64
+}
+
+fn read_exactly<R: Read>(input: &mut R, buf: &mut [u8]) -> DecodeResult<()> {
+    let mut read = 0;
+    while read < buf.len() {
+        match try!(input.read(&mut buf[read..])) {
+            0 => return Err(DecodeError::UnexpectedEnd),
+            n => read += n,
+        }
+    }
+    Ok(())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8 & 0x7f) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn unzigzag(v: u64) -> i64 {
+    (v >> 1) as i64 ^ -((v & 1) as i64)
+}
+
+/// Encodes values into a record, collecting the string table as it goes.
+pub struct Encoder {
+    body: Vec<u8>,
+    strings: Vec<String>,
+    indices: HashMap<String, u64>,
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder { body: vec![], strings: vec![], indices: HashMap::new() }
+    }
+
+    /// The record: the string table, followed by what was encoded.
+    pub fn into_record(self) -> Vec<u8> {
+        let mut record = vec![];
+        write_varint(&mut record, self.strings.len() as u64);
+        for string in &self.strings {
+            write_varint(&mut record, string.len() as u64);
+            record.extend_from_slice(string.as_bytes());
+        }
+        record.extend_from_slice(&self.body);
+        record
+    }
+
+    fn emit_varint(&mut self, v: u64) -> Result<(), EncodeError> {
+        write_varint(&mut self.body, v);
+        Ok(())
+    }
+}
+
+impl rustc_serialize::Encoder for Encoder {
+    type Error = EncodeError;
+
+    fn emit_nil(&mut self) -> Result<(), EncodeError> { Ok(()) }
+
+    fn emit_usize(&mut self, v: usize) -> Result<(), EncodeError> { self.emit_varint(v as u64) }
+    fn emit_u64(&mut self, v: u64) -> Result<(), EncodeError> { self.emit_varint(v) }
+    fn emit_u32(&mut self, v: u32) -> Result<(), EncodeError> { self.emit_varint(v as u64) }
+    fn emit_u16(&mut self, v: u16) -> Result<(), EncodeError> { self.emit_varint(v as u64) }
+    fn emit_u8(&mut self, v: u8) -> Result<(), EncodeError> { self.emit_varint(v as u64) }
+
+    fn emit_isize(&mut self, v: isize) -> Result<(), EncodeError> {
+        self.emit_varint(zigzag(v as i64))
+    }
+    fn emit_i64(&mut self, v: i64) -> Result<(), EncodeError> { self.emit_varint(zigzag(v)) }
+    fn emit_i32(&mut self, v: i32) -> Result<(), EncodeError> {
+        self.emit_varint(zigzag(v as i64))
+    }
+    fn emit_i16(&mut self, v: i16) -> Result<(), EncodeError> {
+        self.emit_varint(zigzag(v as i64))
+    }
+    fn emit_i8(&mut self, v: i8) -> Result<(), EncodeError> { self.emit_varint(zigzag(v as i64)) }
+
+    fn emit_bool(&mut self, v: bool) -> Result<(), EncodeError> {
+        self.body.push(v as u8);
+        Ok(())
+    }
+
+    fn emit_f64(&mut self, v: f64) -> Result<(), EncodeError> {
+        // `f64::to_bits` is newer than the toolchain `emit-ast` builds with
+        let bits = unsafe { mem::transmute::<f64, u64>(v) };
+        for i in 0..8 {
+            self.body.push((bits >> (8 * i)) as u8);
+        }
+        Ok(())
+    }
+
+    fn emit_f32(&mut self, v: f32) -> Result<(), EncodeError> {
+        let bits = unsafe { mem::transmute::<f32, u32>(v) };
+        for i in 0..4 {
+            self.body.push((bits >> (8 * i)) as u8);
+        }
+        Ok(())
+    }
+
+    fn emit_char(&mut self, v: char) -> Result<(), EncodeError> { self.emit_varint(v as u64) }
+
+    fn emit_str(&mut self, v: &str) -> Result<(), EncodeError> {
+        let index = match self.indices.get(v) {
+            Some(&index) => index,
+            None => {
+                let index = self.strings.len() as u64;
+                self.strings.push(v.to_string());
+                self.indices.insert(v.to_string(), index);
+                index
+            },
+        };
+        self.emit_varint(index)
+    }
+
+    fn emit_enum<F>(&mut self, _name: &str, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_enum_variant<F>(&mut self, _name: &str, id: usize, _len: usize, f: F)
+                            -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        try!(self.emit_varint(id as u64));
+        f(self)
+    }
+
+    fn emit_enum_variant_arg<F>(&mut self, _idx: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_enum_struct_variant<F>(&mut self, name: &str, id: usize, len: usize, f: F)
+                                   -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        self.emit_enum_variant(name, id, len, f)
+    }
+
+    fn emit_enum_struct_variant_field<F>(&mut self, _name: &str, _idx: usize, f: F)
+                                         -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_struct<F>(&mut self, _name: &str, _len: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_struct_field<F>(&mut self, _name: &str, _idx: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_tuple<F>(&mut self, _len: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_tuple_arg<F>(&mut self, _idx: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_tuple_struct<F>(&mut self, _name: &str, _len: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_tuple_struct_arg<F>(&mut self, _idx: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_option<F>(&mut self, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_option_none(&mut self) -> Result<(), EncodeError> {
+        self.body.push(0);
+        Ok(())
+    }
+
+    fn emit_option_some<F>(&mut self, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        self.body.push(1);
+        f(self)
+    }
+
+    fn emit_seq<F>(&mut self, len: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        try!(self.emit_varint(len as u64));
+        f(self)
+    }
+
+    fn emit_seq_elt<F>(&mut self, _idx: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_map<F>(&mut self, len: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        try!(self.emit_varint(len as u64));
+        f(self)
+    }
+
+    fn emit_map_elt_key<F>(&mut self, _idx: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+
+    fn emit_map_elt_val<F>(&mut self, _idx: usize, f: F) -> Result<(), EncodeError>
+        where F: FnOnce(&mut Self) -> Result<(), EncodeError> {
+        f(self)
+    }
+}
+
+/// Decodes values from a record, starting with its string table.
+pub struct Decoder<'a> {
+    input: &'a [u8],
+    position: usize,
+    strings: Vec<String>,
+}
+
+impl<'a> Decoder<'a> {
+    /// Reads the string table at the start of `record`.
+    pub fn new(record: &'a [u8]) -> DecodeResult<Decoder<'a>> {
+        let mut decoder = Decoder { input: record, position: 0, strings: vec![] };
+        let count = try!(decoder.read_varint());
+        for _ in 0..count {
+            let length = try!(decoder.read_varint());
+            let bytes = try!(decoder.read_bytes(length));
+            let string = try!(str::from_utf8(bytes).map_err(|_| DecodeError::BadText));
+            decoder.strings.push(string.to_string());
+        }
+        Ok(decoder)
+    }
+
+    /// The number of bytes not yet decoded.
+    pub fn remaining(&self) -> usize {
+        self.input.len() - self.position
+    }
+
+    fn read_bytes(&mut self, n: u64) -> DecodeResult<&'a [u8]> {
+        if n > self.remaining() as u64 {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let start = self.position;
+        self.position += n as usize;
+        Ok(&self.input[start..self.position])
+    }
+
+    fn read_byte(&mut self) -> DecodeResult<u8> {
+        let bytes = try!(self.read_bytes(1));
+        Ok(bytes[0])
+    }
+
+    fn read_varint(&mut self) -> DecodeResult<u64> {
+        let mut v = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = try!(self.read_byte());
+            if shift >= 64 {
+                return Err(DecodeError::Overflow);
+            }
+            v |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+    }
+
+    /// Reads an unsigned number no larger than `max`.
+    fn read_unsigned(&mut self, max: u64) -> DecodeResult<u64> {
+        let v = try!(self.read_varint());
+        if v <= max { Ok(v) } else { Err(DecodeError::Overflow) }
+    }
+
+    /// Reads a signed number between `min` and `max`.
+    fn read_signed(&mut self, min: i64, max: i64) -> DecodeResult<i64> {
+        let v = unzigzag(try!(self.read_varint()));
+        if min <= v && v <= max { Ok(v) } else { Err(DecodeError::Overflow) }
+    }
+
+    /// Reads the length of a sequence or map. Every element takes at least a byte, so a length
+    /// longer than the rest of the record is corrupt (and mustn't be allocated for).
+    fn read_length(&mut self) -> DecodeResult<usize> {
+        let len = try!(self.read_varint());
+        if len <= self.remaining() as u64 {
+            Ok(len as usize)
+        } else {
+            Err(DecodeError::UnexpectedEnd)
+        }
+    }
+
+    fn read_bits(&mut self, n: u64) -> DecodeResult<u64> {
+        let bytes = try!(self.read_bytes(n));
+        Ok(bytes.iter().rev().fold(0, |bits, &byte| (bits << 8) | byte as u64))
+    }
+}
+
+impl<'a> rustc_serialize::Decoder for Decoder<'a> {
+    type Error = DecodeError;
+
+    fn read_nil(&mut self) -> DecodeResult<()> { Ok(()) }
+
+    fn read_usize(&mut self) -> DecodeResult<usize> {
+        self.read_unsigned(::std::usize::MAX as u64).map(|v| v as usize)
+    }
+    fn read_u64(&mut self) -> DecodeResult<u64> { self.read_varint() }
+    fn read_u32(&mut self) -> DecodeResult<u32> {
+        self.read_unsigned(::std::u32::MAX as u64).map(|v| v as u32)
+    }
+    fn read_u16(&mut self) -> DecodeResult<u16> {
+        self.read_unsigned(::std::u16::MAX as u64).map(|v| v as u16)
+    }
+    fn read_u8(&mut self) -> DecodeResult<u8> {
+        self.read_unsigned(::std::u8::MAX as u64).map(|v| v as u8)
+    }
+
+    fn read_isize(&mut self) -> DecodeResult<isize> {
+        self.read_signed(::std::isize::MIN as i64, ::std::isize::MAX as i64).map(|v| v as isize)
+    }
+    fn read_i64(&mut self) -> DecodeResult<i64> { self.read_varint().map(unzigzag) }
+    fn read_i32(&mut self) -> DecodeResult<i32> {
+        self.read_signed(::std::i32::MIN as i64, ::std::i32::MAX as i64).map(|v| v as i32)
+    }
+    fn read_i16(&mut self) -> DecodeResult<i16> {
+        self.read_signed(::std::i16::MIN as i64, ::std::i16::MAX as i64).map(|v| v as i16)
+    }
+    fn read_i8(&mut self) -> DecodeResult<i8> {
+        self.read_signed(::std::i8::MIN as i64, ::std::i8::MAX as i64).map(|v| v as i8)
+    }
+
+    fn read_bool(&mut self) -> DecodeResult<bool> {
+        match try!(self.read_byte()) {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(DecodeError::Invalid(format!("{} is not a boolean", b))),
+        }
+    }
+
+    fn read_f64(&mut self) -> DecodeResult<f64> {
+        self.read_bits(8).map(|bits| unsafe { mem::transmute::<u64, f64>(bits) })
+    }
+    fn read_f32(&mut self) -> DecodeResult<f32> {
+        self.read_bits(4).map(|bits| unsafe { mem::transmute::<u32, f32>(bits as u32) })
+    }
+
+    fn read_char(&mut self) -> DecodeResult<char> {
+        let v = try!(self.read_unsigned(::std::u32::MAX as u64));
+        ::std::char::from_u32(v as u32).ok_or(DecodeError::BadText)
+    }
+
+    fn read_str(&mut self) -> DecodeResult<String> {
+        let index = try!(self.read_varint());
+        self.strings.get(index as usize).cloned().ok_or(DecodeError::BadString(index))
+    }
+
+    fn read_enum<T, F>(&mut self, _name: &str, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_enum_variant<T, F>(&mut self, names: &[&str], mut f: F) -> DecodeResult<T>
+        where F: FnMut(&mut Self, usize) -> DecodeResult<T> {
+        let id = try!(self.read_varint());
+        if id >= names.len() as u64 {
+            return Err(DecodeError::Invalid(format!("No variant {} of {:?}", id, names)));
+        }
+        f(self, id as usize)
+    }
+
+    fn read_enum_variant_arg<T, F>(&mut self, _idx: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_enum_struct_variant<T, F>(&mut self, names: &[&str], f: F) -> DecodeResult<T>
+        where F: FnMut(&mut Self, usize) -> DecodeResult<T> {
+        self.read_enum_variant(names, f)
+    }
+
+    fn read_enum_struct_variant_field<T, F>(&mut self, _name: &str, _idx: usize, f: F)
+                                            -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_struct<T, F>(&mut self, _name: &str, _len: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_struct_field<T, F>(&mut self, _name: &str, _idx: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_tuple<T, F>(&mut self, _len: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_tuple_arg<T, F>(&mut self, _idx: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_tuple_struct<T, F>(&mut self, _name: &str, _len: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_tuple_struct_arg<T, F>(&mut self, _idx: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_option<T, F>(&mut self, mut f: F) -> DecodeResult<T>
+        where F: FnMut(&mut Self, bool) -> DecodeResult<T> {
+        let some = try!(self.read_bool());
+        f(self, some)
+    }
+
+    fn read_seq<T, F>(&mut self, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self, usize) -> DecodeResult<T> {
+        let len = try!(self.read_length());
+        f(self, len)
+    }
+
+    fn read_seq_elt<T, F>(&mut self, _idx: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_map<T, F>(&mut self, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self, usize) -> DecodeResult<T> {
+        let len = try!(self.read_length());
+        f(self, len)
+    }
+
+    fn read_map_elt_key<T, F>(&mut self, _idx: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn read_map_elt_val<T, F>(&mut self, _idx: usize, f: F) -> DecodeResult<T>
+        where F: FnOnce(&mut Self) -> DecodeResult<T> {
+        f(self)
+    }
+
+    fn error(&mut self, err: &str) -> DecodeError {
+        DecodeError::Invalid(err.to_string())
+    }
+}
@@ -0,0 +1,409 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// What changed about `unsafe` between two UASTs, usually two versions of one crate
+//
+// Functions are matched by path (their `name`), and the unsafe blocks and unsafe uses in a pair of
+// functions by their structure, ignoring where they are: spans, indices and snippets are left out
+// of the comparison, so code which merely moved doesn't show up. Those which don't match exactly
+// are then paired up as changed if they are alike (for uses, the same kind of operation on the
+// same callee; for blocks, in order), and the rest are added or removed.
+//
+// Only functions which are unsafe, or have unsafe blocks or uses, in either UAST are reported.
+
+use rustc_serialize::json::{Json,Object};
+
+use std::collections::{HashMap,VecDeque};
+
+use jq;
+use uast::{Crate,FnDecl,Indexed,UASTNode};
+use visit;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Change<T> {
+    Added(T),
+    Removed(T),
+    Changed(T, T),
+}
+
+impl<T> Change<T> {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Change::Added(_) => "added",
+            Change::Removed(_) => "removed",
+            Change::Changed(..) => "changed",
+        }
+    }
+
+    /// The thing in the old UAST, unless it was added.
+    pub fn before(&self) -> Option<&T> {
+        match *self {
+            Change::Added(_) => None,
+            Change::Removed(ref old) | Change::Changed(ref old, _) => Some(old),
+        }
+    }
+
+    /// The thing in the new UAST, unless it was removed.
+    pub fn after(&self) -> Option<&T> {
+        match *self {
+            Change::Removed(_) => None,
+            Change::Added(ref new) | Change::Changed(_, ref new) => Some(new),
+        }
+    }
+
+    fn sign(&self) -> char {
+        match *self {
+            Change::Added(_) => '+',
+            Change::Removed(_) => '-',
+            Change::Changed(..) => '~',
+        }
+    }
+}
+
+/// The changes to one function.
+#[derive(Clone, Debug)]
+pub struct FnDiff<'a> {
+    function: Change<&'a FnDecl>,
+    blocks: Vec<Change<&'a Indexed<UASTNode>>>,
+    uses: Vec<Change<&'a Indexed<UASTNode>>>,
+}
+
+impl<'a> FnDiff<'a> {
+    /// The path of the function.
+    pub fn name(&self) -> &'a str {
+        match self.function {
+            Change::Added(f) | Change::Removed(f) | Change::Changed(_, f) => f.name(),
+        }
+    }
+
+    /// Whether the function was added, removed, or is in both UASTs.
+    pub fn function(&self) -> &Change<&'a FnDecl> { &self.function }
+
+    /// The changes to the `InnerBlock`s of the function's unsafe blocks.
+    pub fn blocks(&self) -> &[Change<&'a Indexed<UASTNode>>] { &self.blocks }
+
+    /// The changes to the function's unsafe uses.
+    pub fn uses(&self) -> &[Change<&'a Indexed<UASTNode>>] { &self.uses }
+
+    /// Whether the function is unsafe in the old and new UASTs, if it is in them.
+    pub fn unsafety(&self) -> (Option<bool>, Option<bool>) {
+        (self.function.before().map(|f| f.unsaf()), self.function.after().map(|f| f.unsaf()))
+    }
+}
+
+/// The number of things added, removed and changed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Counts {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+impl Counts {
+    fn count<T>(&mut self, change: &Change<T>) {
+        match *change {
+            Change::Added(_) => self.added += 1,
+            Change::Removed(_) => self.removed += 1,
+            Change::Changed(..) => self.changed += 1,
+        }
+    }
+
+    fn to_json(&self) -> Json {
+        let mut obj = Object::new();
+        obj.insert("added".to_string(), Json::U64(self.added as u64));
+        obj.insert("removed".to_string(), Json::U64(self.removed as u64));
+        obj.insert("changed".to_string(), Json::U64(self.changed as u64));
+        Json::Object(obj)
+    }
+}
+
+pub struct CrateDiff<'a> {
+    old: &'a Crate,
+    new: &'a Crate,
+    functions: Vec<FnDiff<'a>>,
+}
+
+impl<'a> CrateDiff<'a> {
+    pub fn new(old: &'a Crate, new: &'a Crate) -> CrateDiff<'a> {
+        let mut functions = vec![];
+        for change in match_functions(old.functions(), new.functions()) {
+            let (old_blocks, old_uses) = change.before().map_or_else(no_parts, |f| unsafe_parts(f));
+            let (new_blocks, new_uses) = change.after().map_or_else(no_parts, |f| unsafe_parts(f));
+            let blocks = match_items(old_blocks, new_blocks, |_| String::new());
+            let uses = match_items(old_uses, new_uses, use_identity);
+            let unsafety_changed = match change {
+                Change::Changed(old, new) => old.unsaf() != new.unsaf(),
+                Change::Added(f) | Change::Removed(f) => f.unsaf(),
+            };
+            if unsafety_changed || !blocks.is_empty() || !uses.is_empty() {
+                functions.push(FnDiff { function: change, blocks: blocks, uses: uses });
+            }
+        }
+        CrateDiff { old: old, new: new, functions: functions }
+    }
+
+    pub fn before(&self) -> &'a Crate { self.old }
+    pub fn after(&self) -> &'a Crate { self.new }
+
+    /// The functions with changes to do with `unsafe`, in the order of the old UAST, followed by
+    /// those only in the new one.
+    pub fn functions(&self) -> &[FnDiff<'a>] { &self.functions }
+
+    pub fn is_empty(&self) -> bool { self.functions.is_empty() }
+
+    /// Unsafe functions added (including safe ones made unsafe), removed (including unsafe ones
+    /// made safe), and changed (unsafe in both, with changed blocks or uses).
+    pub fn unsafe_function_counts(&self) -> Counts {
+        let mut counts = Counts::default();
+        for function in &self.functions {
+            match function.unsafety() {
+                (Some(true), Some(true)) => counts.changed += 1,
+                (Some(true), _) => counts.removed += 1,
+                (_, Some(true)) => counts.added += 1,
+                _ => {},
+            }
+        }
+        counts
+    }
+
+    pub fn unsafe_block_counts(&self) -> Counts {
+        let mut counts = Counts::default();
+        for change in self.functions.iter().flat_map(|f| f.blocks.iter()) {
+            counts.count(change);
+        }
+        counts
+    }
+
+    pub fn unsafe_use_counts(&self) -> Counts {
+        let mut counts = Counts::default();
+        for change in self.functions.iter().flat_map(|f| f.uses.iter()) {
+            counts.count(change);
+        }
+        counts
+    }
+
+    /// A report for people, like:
+    ///
+    /// ```text
+    /// ~ fn foo::bar (now unsafe)
+    ///     + unsafe block at src/lib.rs:3:5: 5:6 `unsafe { *p }`
+    ///     - Deref at src/lib.rs:9:13: 9:15 `*q`
+    /// ```
+    pub fn to_text(&self) -> String {
+        let mut out = format!("unsafe changes from {} to {}\n", describe_crate(self.old),
+                              describe_crate(self.new));
+        for function in &self.functions {
+            let unsaf = function.function.after().or(function.function.before())
+                .map_or(false, |f| f.unsaf());
+            out.push_str(&format!("\n{} {}fn {}", function.function.sign(),
+                                  if unsaf { "unsafe " } else { "" }, function.name()));
+            match function.unsafety() {
+                (Some(true), Some(false)) => out.push_str(" (no longer unsafe)"),
+                (Some(false), Some(true)) => out.push_str(" (now unsafe)"),
+                _ => {},
+            }
+            out.push('\n');
+            for change in &function.blocks {
+                out.push_str(&describe_change(change, |_| "unsafe block".to_string()));
+            }
+            for change in &function.uses {
+                out.push_str(&describe_change(change, describe_use));
+            }
+        }
+        if self.is_empty() {
+            out.push_str("\nNo changes to unsafe code\n");
+        }
+        out.push('\n');
+        for &(what, counts) in &[("unsafe functions", self.unsafe_function_counts()),
+                                 ("unsafe blocks", self.unsafe_block_counts()),
+                                 ("unsafe uses", self.unsafe_use_counts())] {
+            out.push_str(&format!("{}: {} added, {} removed, {} changed\n",
+                                  what, counts.added, counts.removed, counts.changed));
+        }
+        out
+    }
+
+    /// A report for tools:
+    ///
+    /// ```text
+    /// {"old": {"name", "version"}, "new": {..},
+    ///  "functions": [{"name", "change", "old_unsafe", "new_unsafe",
+    ///                 "blocks": [{"change", "old", "new"}], "uses": [..]}],
+    ///  "summary": {"functions": {"added", "removed", "changed"}, "blocks": .., "uses": ..}}
+    /// ```
+    ///
+    /// where blocks and uses are `Indexed` nodes as encoded in UASTs, or null.
+    pub fn to_json(&self) -> Json {
+        let functions = self.functions.iter().map(|function| {
+            let (old_unsafe, new_unsafe) = function.unsafety();
+            let mut obj = Object::new();
+            obj.insert("name".to_string(), Json::String(function.name().to_string()));
+            obj.insert("change".to_string(), Json::String(function.function.name().to_string()));
+            obj.insert("old_unsafe".to_string(), old_unsafe.map_or(Json::Null, Json::Boolean));
+            obj.insert("new_unsafe".to_string(), new_unsafe.map_or(Json::Null, Json::Boolean));
+            obj.insert("blocks".to_string(), changes_to_json(&function.blocks));
+            obj.insert("uses".to_string(), changes_to_json(&function.uses));
+            Json::Object(obj)
+        }).collect();
+        let mut summary = Object::new();
+        summary.insert("functions".to_string(), self.unsafe_function_counts().to_json());
+        summary.insert("blocks".to_string(), self.unsafe_block_counts().to_json());
+        summary.insert("uses".to_string(), self.unsafe_use_counts().to_json());
+
+        let mut obj = Object::new();
+        obj.insert("old".to_string(), crate_to_json(self.old));
+        obj.insert("new".to_string(), crate_to_json(self.new));
+        obj.insert("functions".to_string(), Json::Array(functions));
+        obj.insert("summary".to_string(), Json::Object(summary));
+        Json::Object(obj)
+    }
+}
+
+/// Pairs up functions with the same path, in order if several share one.
+fn match_functions<'a>(old: &'a [FnDecl], new: &'a [FnDecl]) -> Vec<Change<&'a FnDecl>> {
+    let mut by_name: HashMap<&str, VecDeque<&FnDecl>> = HashMap::new();
+    for function in new {
+        by_name.entry(function.name()).or_insert_with(VecDeque::new).push_back(function);
+    }
+    let mut changes = vec![];
+    for function in old {
+        match by_name.get_mut(function.name()).and_then(|fs| fs.pop_front()) {
+            Some(new) => changes.push(Change::Changed(function, new)),
+            None => changes.push(Change::Removed(function)),
+        }
+    }
+    for function in new {
+        if let Some(fs) = by_name.get_mut(function.name()) {
+            if let Some(new) = fs.pop_front() {
+                changes.push(Change::Added(new));
+            }
+        }
+    }
+    changes
+}
+
+fn no_parts<'a>() -> (Vec<&'a Indexed<UASTNode>>, Vec<&'a Indexed<UASTNode>>) {
+    (vec![], vec![])
+}
+
+/// The unsafe blocks (as the `InnerBlock` nodes holding them) and unsafe uses of a function.
+fn unsafe_parts(function: &FnDecl) -> (Vec<&Indexed<UASTNode>>, Vec<&Indexed<UASTNode>>) {
+    visit::unsafe_sites(function).into_iter().partition(|node| node.item().block().is_some())
+}
+
+/// Matches up the `old` and `new` nodes with the same structure, then those with the same
+/// `identity`, in order. The changes come in the order of `old`, with the added nodes last.
+fn match_items<'a, F>(old: Vec<&'a Indexed<UASTNode>>,
+                      new: Vec<&'a Indexed<UASTNode>>,
+                      identity: F) -> Vec<Change<&'a Indexed<UASTNode>>>
+    where F: Fn(&Indexed<UASTNode>) -> String {
+    let mut unmatched: Vec<Option<&Indexed<UASTNode>>> = new.into_iter().map(Some).collect();
+    let mut by_structure: HashMap<String, VecDeque<usize>> = HashMap::new();
+    for (i, node) in unmatched.iter().enumerate() {
+        by_structure.entry(structure(node.unwrap())).or_insert_with(VecDeque::new).push_back(i);
+    }
+    let mut leftover = vec![];
+    for node in old {
+        match by_structure.get_mut(&structure(node)).and_then(|is| is.pop_front()) {
+            Some(i) => unmatched[i] = None,
+            None => leftover.push(node),
+        }
+    }
+    let mut changes = vec![];
+    for node in leftover {
+        let alike = unmatched.iter()
+            .position(|new| new.map_or(false, |new| identity(new) == identity(node)));
+        match alike {
+            Some(i) => changes.push(Change::Changed(node, unmatched[i].take().unwrap())),
+            None => changes.push(Change::Removed(node)),
+        }
+    }
+    changes.extend(unmatched.into_iter().filter_map(|new| new.map(Change::Added)));
+    changes
+}
+
+/// What a node is, regardless of where it is: its encoding, without spans, indices or snippets
+/// (at any depth).
+fn structure(node: &Indexed<UASTNode>) -> String {
+    fn strip(json: Json) -> Json {
+        match json {
+            Json::Object(obj) => Json::Object(obj.into_iter()
+                .filter(|&(ref key, _)| match &key[..] {
+                    "index" | "span" | "span_info" | "snippet" | "truncated" => false,
+                    _ => true,
+                })
+                .map(|(key, value)| (key, strip(value)))
+                .collect()),
+            Json::Array(items) => Json::Array(items.into_iter().map(strip).collect()),
+            other => other,
+        }
+    }
+    let json = Json::from_str(&jq::encode(node)).expect("The encoded UAST should be valid JSON");
+    strip(json).to_string()
+}
+
+/// The kind of a use, and what it calls.
+fn use_identity(node: &Indexed<UASTNode>) -> String {
+    match *node.item() {
+        UASTNode::Call(_, _, ref callee) => format!("Call {}", callee.path().unwrap_or("")),
+        ref item => item.kind().to_string(),
+    }
+}
+
+fn describe_use(node: &Indexed<UASTNode>) -> String {
+    match *node.item() {
+        UASTNode::Call(_, ref ffi, ref callee) => {
+            let mut description = format!("call to {}", callee.path().unwrap_or("<fn pointer>"));
+            if ffi.is_ffi() {
+                description.push_str(" (FFI)");
+            } else if ffi.is_intrinsic() {
+                description.push_str(" (intrinsic)");
+            }
+            description
+        },
+        ref item => item.kind().to_string(),
+    }
+}
+
+fn describe_change<F>(change: &Change<&Indexed<UASTNode>>, describe: F) -> String
+    where F: Fn(&Indexed<UASTNode>) -> String {
+    let location = |node: &Indexed<UASTNode>| format!("{} `{}`", node.span(), node.snippet());
+    match *change {
+        Change::Changed(old, new) => {
+            format!("    ~ {} at {}\n      now {} at {}\n",
+                    describe(old), location(old), describe(new), location(new))
+        },
+        Change::Added(node) | Change::Removed(node) => {
+            format!("    {} {} at {}\n", change.sign(), describe(node), location(node))
+        },
+    }
+}
+
+fn describe_crate(krate: &Crate) -> String {
+    match krate.meta().version() {
+        Some(version) => format!("{} {}", krate.name(), version),
+        None => krate.name().to_string(),
+    }
+}
+
+fn crate_to_json(krate: &Crate) -> Json {
+    let mut obj = Object::new();
+    obj.insert("name".to_string(), Json::String(krate.name().to_string()));
+    obj.insert("version".to_string(),
+               krate.meta().version().map_or(Json::Null, |v| Json::String(v.to_string())));
+    Json::Object(obj)
+}
+
+fn changes_to_json(changes: &[Change<&Indexed<UASTNode>>]) -> Json {
+    let node_to_json = |node: Option<&&Indexed<UASTNode>>| match node {
+        Some(node) => {
+            Json::from_str(&jq::encode(*node)).expect("The encoded UAST should be valid JSON")
+        },
+        None => Json::Null,
+    };
+    Json::Array(changes.iter().map(|change| {
+        let mut obj = Object::new();
+        obj.insert("change".to_string(), Json::String(change.name().to_string()));
+        obj.insert("old".to_string(), node_to_json(change.before()));
+        obj.insert("new".to_string(), node_to_json(change.after()));
+        Json::Object(obj)
+    }).collect())
+}
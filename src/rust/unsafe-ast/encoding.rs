@@ -0,0 +1,294 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// The tagged encoding of UAST nodes
+//
+// By default `rustc_serialize` encodes a `UASTNode::Call(Unsafe, FFI, Callee)` as
+//
+//     {"variant": "Call", "fields": [{"unsaf": ..}, {"is_ffi": .., ..}, {"path": .., ..}]}
+//
+// and nodes without fields, like `Deref`, as bare strings, so tools have to dig fields out by
+// position. The tagged encoding instead makes every node an object with a "kind", and names its
+// fields:
+//
+//     {"kind": "Deref"}
+//     {"kind": "Call", "unsafe": true, "ffi": true, "intrinsic": false, "abi": "C",
+//      "callee": "libc::abort", "callee_crate": "libc"}
+//     {"kind": "InnerBlock", "block": {"size": .., "unsaf": .., "contents": [..]}}
+//
+// A tagged UAST says so in its `meta.encoding`. `data-analysis/to-tagged.jq` does the same
+// conversion for existing datasets.
+//
+// This file also upgrades records from before the UAST was versioned, which lack the fields that
+// were added since, so that they still decode.
+
+use rustc_serialize::json::{Json,Object};
+
+/// The snippet length of emitters from before the UAST was versioned. They cut snippets of over 40
+/// bytes down to 39 characters and a '#', without otherwise marking them truncated.
+const OLD_SNIPPET_LENGTH: usize = 40;
+
+/// Rewrites every UAST node in `json`, which may be a whole encoded UAST or any part of one, into
+/// the tagged encoding. Fields which records from before the UAST was versioned lack are given
+/// the same values as `upgrade` gives them. Tagging a tagged UAST gives it back unchanged.
+pub fn tag(json: Json) -> Json {
+    match json {
+        Json::Object(obj) => {
+            // Nodes are the `item`s of `Indexed`s
+            let is_indexed = obj.contains_key("index") && obj.contains_key("item");
+            Json::Object(obj.into_iter().map(|(key, value)| {
+                let value = tag(value);
+                if is_indexed && key == "item" {
+                    (key, tag_node(value))
+                } else if key == "meta" {
+                    (key, with_encoding(value, "tagged"))
+                } else {
+                    (key, value)
+                }
+            }).collect())
+        },
+        Json::Array(items) => Json::Array(items.into_iter().map(tag).collect()),
+        other => other,
+    }
+}
+
+/// Converts one (default encoded) node into the tagged encoding. Its children must already be
+/// converted. Nodes already in the tagged encoding are left alone, so tagging a tagged UAST, or a
+/// dataset of both encodings, is safe.
+fn tag_node(node: Json) -> Json {
+    let mut tagged = Object::new();
+    match node {
+        Json::String(kind) => {
+            tagged.insert("kind".to_string(), Json::String(kind));
+        },
+        Json::Object(mut obj) => {
+            // Already tagged
+            if obj.contains_key("kind") {
+                return Json::Object(obj);
+            }
+            let kind = obj.remove("variant").unwrap_or(Json::Null);
+            let mut fields = match obj.remove("fields") {
+                Some(Json::Array(fields)) => fields,
+                _ => vec![],
+            };
+            if kind.as_string() == Some("Call") {
+                let no = || Json::Boolean(false);
+                tagged.insert("unsafe".to_string(), field(fields.get(0), "unsaf", no()));
+                tagged.insert("ffi".to_string(), field(fields.get(1), "is_ffi", no()));
+                tagged.insert("intrinsic".to_string(),
+                              field(fields.get(1), "is_intrinsic", no()));
+                tagged.insert("abi".to_string(),
+                              field(fields.get(1), "abi", Json::String(String::new())));
+                tagged.insert("callee".to_string(), field(fields.get(2), "path", Json::Null));
+                tagged.insert("callee_crate".to_string(),
+                              field(fields.get(2), "krate", Json::Null));
+            } else {
+                // `Closure` and `InnerBlock` have just their block
+                tagged.insert("block".to_string(), fields.pop().unwrap_or(Json::Null));
+            }
+            tagged.insert("kind".to_string(), kind);
+        },
+        other => return other,
+    }
+    Json::Object(tagged)
+}
+
+/// The field `key` of a (default encoded) struct, or `default` if it has none.
+fn field(obj: Option<&Json>, key: &str, default: Json) -> Json {
+    obj.and_then(|obj| obj.find(key)).cloned().unwrap_or(default)
+}
+
+/// Records `encoding` as the encoding of the UAST with metadata `meta`.
+fn with_encoding(meta: Json, encoding: &str) -> Json {
+    match meta {
+        Json::Object(mut meta) => {
+            meta.insert("encoding".to_string(), Json::String(encoding.to_string()));
+            Json::Object(meta)
+        },
+        other => other,
+    }
+}
+
+/// Rewrites every tagged UAST node in `json` back into the default encoding, which is what the
+/// `RustcDecodable` impls expect. Nodes already in the default encoding are left alone.
+pub fn untag(json: Json) -> Json {
+    match json {
+        Json::Object(obj) => {
+            let is_indexed = obj.contains_key("index") && obj.contains_key("item");
+            Json::Object(obj.into_iter().map(|(key, value)| {
+                let value = untag(value);
+                if is_indexed && key == "item" {
+                    (key, untag_node(value))
+                } else if key == "meta" {
+                    (key, with_encoding(value, "default"))
+                } else {
+                    (key, value)
+                }
+            }).collect())
+        },
+        Json::Array(items) => Json::Array(items.into_iter().map(untag).collect()),
+        other => other,
+    }
+}
+
+/// Converts one tagged node into the default encoding. Its children must already be converted.
+fn untag_node(node: Json) -> Json {
+    let mut obj = match node {
+        Json::Object(obj) => obj,
+        other => return other,
+    };
+    let kind = match obj.remove("kind") {
+        Some(Json::String(kind)) => kind,
+        Some(other) => {
+            obj.insert("kind".to_string(), other);
+            return Json::Object(obj);
+        },
+        // Not tagged
+        None => return Json::Object(obj),
+    };
+    let fields = if kind == "Call" {
+        let take = |obj: &mut Object, key: &str| obj.remove(key).unwrap_or(Json::Null);
+        vec![
+            object(vec![("unsaf", take(&mut obj, "unsafe"))]),
+            object(vec![("is_ffi", take(&mut obj, "ffi")),
+                        ("is_intrinsic", take(&mut obj, "intrinsic")),
+                        ("abi", take(&mut obj, "abi"))]),
+            object(vec![("path", take(&mut obj, "callee")),
+                        ("krate", take(&mut obj, "callee_crate"))]),
+        ]
+    } else if kind == "Closure" || kind == "InnerBlock" {
+        vec![obj.remove("block").unwrap_or(Json::Null)]
+    } else {
+        return Json::String(kind);
+    };
+    object(vec![("variant", Json::String(kind)), ("fields", Json::Array(fields))])
+}
+
+/// Fills in the fields that a UAST (in the default encoding) from before the UAST was versioned
+/// lacks, so that it decodes. What those emitters did not record is given placeholder values:
+/// `meta.schema_version` is 0, signatures are empty, functions are unexported and `Inherited`, and
+/// calls have an unknown callee and ABI. UASTs which have metadata are returned unchanged.
+///
+/// A snippet of exactly 40 characters ending in '#' is taken to be truncated, and loses the '#'.
+/// This is a guess: an old snippet which really was 40 ASCII characters ending in '#', like
+/// `r#"..."#`, is damaged the same way. Only records without metadata, whose items lack
+/// `truncated`, are guessed about.
+pub fn upgrade(json: Json) -> Json {
+    match json {
+        Json::Object(mut krate) => {
+            if krate.contains_key("meta") {
+                return Json::Object(krate);
+            }
+            default(&mut krate, "unsafe_impls", Json::Array(vec![]));
+            default(&mut krate, "unsafe_traits", Json::Array(vec![]));
+            default(&mut krate, "module_tree", Json::Null);
+            default(&mut krate, "meta", object(vec![
+                ("schema_version", Json::U64(0)),
+                ("package", Json::Null),
+                ("version", Json::Null),
+                ("target_kind", Json::String("unknown".to_string())),
+                ("features", Json::Array(vec![])),
+                ("compiler_version", Json::String("unknown".to_string())),
+                ("timestamp", Json::U64(0)),
+                ("snippet_length", Json::U64(OLD_SNIPPET_LENGTH as u64)),
+                ("encoding", Json::String("default".to_string())),
+            ]));
+            if let Some(&mut Json::Array(ref mut functions)) = krate.get_mut("functions") {
+                for function in functions {
+                    if let Json::Object(ref mut function) = *function {
+                        upgrade_fn(function);
+                    }
+                }
+            }
+            Json::Object(krate)
+        },
+        other => other,
+    }
+}
+
+fn upgrade_fn(function: &mut Object) {
+    default(function, "span_info", Json::Null);
+    default(function, "sig", object(vec![
+        ("abi", Json::String(String::new())),
+        ("inputs", Json::Array(vec![])),
+        ("output", Json::String(String::new())),
+        ("raw_ptr_input", Json::Boolean(false)),
+        ("raw_ptr_output", Json::Boolean(false)),
+        ("type_params", Json::U64(0)),
+    ]));
+    default(function, "vis", Json::String("Inherited".to_string()));
+    default(function, "exported", Json::Boolean(false));
+    if let Some(&mut Json::Object(ref mut block)) = function.get_mut("block") {
+        upgrade_contents(block);
+    }
+}
+
+/// Like `upgrade`, for a block on its own, like the lines `blocks` in `unsafe.jq` prints. Blocks
+/// have no metadata to tell old ones apart by, but the items of new ones all have `truncated`,
+/// and are left unchanged.
+pub fn upgrade_block(json: Json) -> Json {
+    match json {
+        Json::Object(mut block) => {
+            upgrade_contents(&mut block);
+            Json::Object(block)
+        },
+        other => other,
+    }
+}
+
+fn upgrade_contents(block: &mut Object) {
+    let contents = match block.get_mut("contents") {
+        Some(&mut Json::Array(ref mut contents)) => contents,
+        _ => return,
+    };
+    for indexed in contents {
+        let indexed = match *indexed {
+            Json::Object(ref mut indexed) => indexed,
+            _ => continue,
+        };
+        default(indexed, "span_info", Json::Null);
+        if !indexed.contains_key("truncated") {
+            let mut truncated = false;
+            if let Some(&mut Json::String(ref mut snippet)) = indexed.get_mut("snippet") {
+                if snippet.chars().count() == OLD_SNIPPET_LENGTH && snippet.ends_with('#') {
+                    snippet.pop();
+                    truncated = true;
+                }
+            }
+            indexed.insert("truncated".to_string(), Json::Boolean(truncated));
+        }
+        let fields = match indexed.get_mut("item") {
+            Some(&mut Json::Object(ref mut item)) => match item.get_mut("fields") {
+                Some(&mut Json::Array(ref mut fields)) => fields,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        match fields.len() {
+            // A `Call` without its `Callee`
+            2 => {
+                if let Json::Object(ref mut ffi) = fields[1] {
+                    default(ffi, "is_intrinsic", Json::Boolean(false));
+                    default(ffi, "abi", Json::String(String::new()));
+                }
+                fields.push(object(vec![("path", Json::Null), ("krate", Json::Null)]));
+            },
+            // A `Closure` or `InnerBlock`
+            1 => {
+                if let Json::Object(ref mut inner) = fields[0] {
+                    upgrade_contents(inner);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Sets `key` in `obj` to `value`, unless it is already set.
+fn default(obj: &mut Object, key: &str, value: Json) {
+    if !obj.contains_key(key) {
+        obj.insert(key.to_string(), value);
+    }
+}
+
+fn object(pairs: Vec<(&str, Json)>) -> Json {
+    Json::Object(pairs.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
@@ -0,0 +1,385 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// HTML reports of the unsafe code in crates, for browsing it with its source
+//
+// A report has an index page, which counts the unsafe functions, unsafe blocks and unsafe uses in
+// each source file and links to each of them, and a page for each source file, which shows it with
+// those sites highlighted from their spans. Sites nest (a `Deref` in an unsafe block in an unsafe
+// function), so each piece of source gets the classes of all the sites around it, and the tooltip
+// of the innermost one.
+//
+// Sites whose spans aren't in the crate's source, such as those in the expansions of external
+// macros, are listed on the index page without links. So are those in files named by absolute
+// paths or paths with `..` in them, whose source is somewhere other than the crate's directory.
+
+use std::collections::{BTreeMap,BTreeSet};
+use std::fmt::Write as FmtWrite;
+use std::fs::{self,File};
+use std::io::{self,Read,Write};
+use std::path::Path;
+
+use atomic;
+use span::Span;
+use uast::{Crate,FnDecl,Indexed,SpanInfo,UASTNode};
+use visit::{self,Context,UastVisitor};
+
+const STYLE: &'static str = "
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { padding: 0.2em 0.8em; text-align: left; border-bottom: 1px solid #ddd; }
+td.count { text-align: right; }
+pre { font-size: 0.9em; line-height: 1.3; }
+.ln { display: inline-block; width: 4em; color: #999; user-select: none; }
+.unsafe-fn { background: #fff3c4; }
+.unsafe-block { background: #ffd59e; }
+.unsafe-use { background: #f4a0a0; outline: 1px solid #c62828; }
+code { background: #f4f4f4; }
+";
+
+/// What sort of unsafe site something is, which is how it is highlighted.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Class {
+    UnsafeFn,
+    UnsafeBlock,
+    UnsafeUse,
+}
+
+impl Class {
+    /// The CSS class of the site.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Class::UnsafeFn => "unsafe-fn",
+            Class::UnsafeBlock => "unsafe-block",
+            Class::UnsafeUse => "unsafe-use",
+        }
+    }
+}
+
+/// An unsafe function, unsafe block or unsafe use, and where it is.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Site {
+    id: usize,
+    class: Class,
+    label: String,
+    krate: String,
+    function: String,
+    snippet: String,
+    span: Option<Span>,
+}
+
+impl Site {
+    /// Unique within the report, and the site's anchor on its file's page is `site-<id>`.
+    pub fn id(&self) -> usize { self.id }
+    pub fn class(&self) -> Class { self.class }
+    /// "unsafe fn", "unsafe block", or the kind of use (see `UASTNode::kind`).
+    pub fn label(&self) -> &str { &self.label }
+    pub fn krate(&self) -> &str { &self.krate }
+    pub fn function(&self) -> &str { &self.function }
+    pub fn snippet(&self) -> &str { &self.snippet }
+    /// Where the site is, if that is in the crate's source.
+    pub fn span(&self) -> Option<&Span> { self.span.as_ref() }
+
+    fn describe(&self) -> String {
+        format!("{} in {}::{}", self.label, self.krate, self.function)
+    }
+}
+
+/// Collects the sites of one crate.
+struct Collector<'a> {
+    krate: &'a Crate,
+    sites: Vec<Site>,
+}
+
+impl<'a> Collector<'a> {
+    fn push(&mut self, class: Class, label: &str, function: &FnDecl, snippet: &str, span: &str,
+            span_info: Option<&SpanInfo>) {
+        self.sites.push(Site {
+            id: 0,
+            class: class,
+            label: label.to_string(),
+            krate: self.krate.name().to_string(),
+            function: function.name().to_string(),
+            snippet: snippet.to_string(),
+            span: Span::of(span, span_info).and_then(|span| {
+                if span.is_real() && span.is_within_source() { Some(span) } else { None }
+            }),
+        });
+    }
+}
+
+impl<'a> UastVisitor<'a> for Collector<'a> {
+    fn visit_fn(&mut self, function: &'a FnDecl, cx: &Context<'a>) {
+        if function.unsaf() {
+            self.push(Class::UnsafeFn, "unsafe fn", function, "", function.span(),
+                      function.span_info());
+        }
+        visit::walk_fn(self, function, cx)
+    }
+
+    fn visit_node(&mut self, node: &'a Indexed<UASTNode>, cx: &Context<'a>) {
+        if visit::is_unsafe_site(node) {
+            let function = cx.function().expect("Nodes should be in functions");
+            let (class, label) = match node.item().block() {
+                Some(_) => (Class::UnsafeBlock, "unsafe block"),
+                None => (Class::UnsafeUse, node.item().kind()),
+            };
+            self.push(class, label, function, node.snippet(), node.span(), node.span_info());
+        }
+        visit::walk_node(self, node, cx)
+    }
+}
+
+/// `text`, escaped for HTML.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            escape(title), STYLE, body)
+}
+
+/// The numbers of unsafe functions, unsafe blocks and unsafe uses in `sites`.
+fn counts(sites: &[&Site]) -> (usize, usize, usize) {
+    let count = |class| sites.iter().filter(|site| site.class == class).count();
+    (count(Class::UnsafeFn), count(Class::UnsafeBlock), count(Class::UnsafeUse))
+}
+
+/// An HTML report of the unsafe code in some crates, usually the targets of one package.
+#[derive(Clone, Debug)]
+pub struct Report {
+    title: String,
+    sites: Vec<Site>,
+    pages: BTreeMap<String, String>,
+}
+
+impl Report {
+    pub fn new(crates: &[Crate]) -> Report {
+        let mut sites = vec![];
+        for krate in crates {
+            let mut collector = Collector { krate: krate, sites: vec![] };
+            collector.visit_crate(krate);
+            sites.extend(collector.sites);
+        }
+        // In the order of the source, so the sites of a file are listed as they are in it, with
+        // those which are nowhere last
+        sites.sort_by(|a, b| match (&a.span, &b.span) {
+            (&Some(ref a), &Some(ref b)) => a.cmp_position(b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+        for (id, site) in sites.iter_mut().enumerate() {
+            site.id = id + 1;
+        }
+
+        // A page for each file, named after it, with just the characters that are safe in paths
+        // and URLs
+        let mut pages = BTreeMap::new();
+        let mut names = BTreeSet::new();
+        let safe = |c: char| (c.is_alphanumeric() && (c as u32) < 0x80) || c == '.' || c == '-';
+        for file in sites.iter().filter_map(|site| site.span.as_ref()).map(|span| span.file()) {
+            if pages.contains_key(file) {
+                continue;
+            }
+            let base: String = file.trim_left_matches('/').chars()
+                .map(|c| if safe(c) { c } else { '_' })
+                .collect();
+            let mut name = format!("{}.html", base);
+            let mut n = 1;
+            while names.contains(&name) || name == "index.html" {
+                n += 1;
+                name = format!("{}-{}.html", base, n);
+            }
+            names.insert(name.clone());
+            pages.insert(file.to_string(), name);
+        }
+
+        let mut names: Vec<&str> = crates.iter().map(|krate| krate.name()).collect();
+        names.dedup();
+        Report { title: format!("Unsafe code in {}", names.join(", ")), sites: sites, pages: pages }
+    }
+
+    /// All the sites, in the order of the source.
+    pub fn sites(&self) -> &[Site] {
+        &self.sites
+    }
+
+    /// The source files with sites in them, and the names of their pages.
+    pub fn pages(&self) -> &BTreeMap<String, String> {
+        &self.pages
+    }
+
+    fn sites_in(&self, file: &str) -> Vec<&Site> {
+        self.sites.iter().filter(|site| site.span.as_ref().map_or(false, |s| s.file() == file))
+            .collect()
+    }
+
+    /// The index page. The files in `missing` have no pages, as their source couldn't be read.
+    pub fn render_index(&self, missing: &[String]) -> String {
+        let mut body = format!("<h1>{}</h1>\n", escape(&self.title));
+        let all: Vec<&Site> = self.sites.iter().collect();
+        let (fns, blocks, uses) = counts(&all);
+        write!(body, "<p>{} unsafe functions, {} unsafe blocks and {} unsafe uses.</p>\n",
+               fns, blocks, uses).unwrap();
+
+        body.push_str("<table>\n<tr><th>File</th><th>Unsafe fns</th><th>Unsafe blocks</th>\
+                       <th>Unsafe uses</th></tr>\n");
+        for (file, name) in &self.pages {
+            let (fns, blocks, uses) = counts(&self.sites_in(file));
+            let link = if missing.contains(file) {
+                format!("{} (source not found)", escape(file))
+            } else {
+                format!("<a href=\"{}\">{}</a>", escape(name), escape(file))
+            };
+            write!(body, "<tr><td>{}</td><td class=\"count\">{}</td><td class=\"count\">{}</td>\
+                          <td class=\"count\">{}</td></tr>\n", link, fns, blocks, uses).unwrap();
+        }
+        body.push_str("</table>\n");
+
+        for (file, name) in &self.pages {
+            write!(body, "<h2>{}</h2>\n<ul>\n", escape(file)).unwrap();
+            for site in self.sites_in(file) {
+                let span = site.span.as_ref().expect("The site should be in the file");
+                let location = format!("line {}", span.lo_line());
+                let location = if missing.contains(file) {
+                    location
+                } else {
+                    format!("<a href=\"{}#site-{}\">{}</a>", escape(name), site.id, location)
+                };
+                write!(body, "<li>{}: {}{}</li>\n", location, escape(&site.describe()),
+                       snippet_html(site)).unwrap();
+            }
+            body.push_str("</ul>\n");
+        }
+
+        let elsewhere: Vec<&Site> = self.sites.iter().filter(|site| site.span.is_none()).collect();
+        if !elsewhere.is_empty() {
+            body.push_str("<h2>Outside the crate's source</h2>\n<ul>\n");
+            for site in elsewhere {
+                write!(body, "<li>{}{}</li>\n", escape(&site.describe()), snippet_html(site))
+                    .unwrap();
+            }
+            body.push_str("</ul>\n");
+        }
+        page(&self.title, &body)
+    }
+
+    /// The page of `file`, whose contents are `source`.
+    pub fn render_file(&self, file: &str, source: &str) -> String {
+        let sites = self.sites_in(file);
+        let (fns, blocks, uses) = counts(&sites);
+        let mut body = format!("<p><a href=\"index.html\">{}</a></p>\n<h1>{}</h1>\n",
+                               escape(&self.title), escape(file));
+        write!(body, "<p>{} unsafe functions, {} unsafe blocks and {} unsafe uses.</p>\n<ul>\n",
+               fns, blocks, uses).unwrap();
+        for site in &sites {
+            let span = site.span.as_ref().expect("The site should be in the file");
+            write!(body, "<li><a href=\"#site-{}\">line {}</a>: {}{}</li>\n", site.id,
+                   span.lo_line(), escape(&site.describe()), snippet_html(site)).unwrap();
+        }
+        body.push_str("</ul>\n<pre>\n");
+        for (n, line) in source.lines().enumerate() {
+            body.push_str(&render_line(n as u64 + 1, line, &sites));
+        }
+        body.push_str("</pre>\n");
+        page(&format!("{} - {}", file, self.title), &body)
+    }
+
+    /// Writes the report to `out_dir`, reading the source files from `src_dir`, and returns the
+    /// files which couldn't be read. Each page is written atomically, so a report being rewritten
+    /// never has half-written pages.
+    pub fn write(&self, src_dir: &Path, out_dir: &Path) -> io::Result<Vec<String>> {
+        try!(fs::create_dir_all(out_dir));
+        let mut missing = vec![];
+        for (file, name) in &self.pages {
+            let mut source = String::new();
+            match File::open(src_dir.join(file)).and_then(|mut f| f.read_to_string(&mut source)) {
+                Ok(_) => {
+                    let page = self.render_file(file, &source);
+                    try!(atomic::write_atomically(&out_dir.join(name),
+                                                  |out| out.write_all(page.as_bytes())));
+                },
+                Err(_) => missing.push(file.clone()),
+            }
+        }
+        let index = self.render_index(&missing);
+        try!(atomic::write_atomically(&out_dir.join("index.html"),
+                                      |out| out.write_all(index.as_bytes())));
+        Ok(missing)
+    }
+}
+
+fn snippet_html(site: &Site) -> String {
+    if site.snippet.is_empty() {
+        String::new()
+    } else {
+        format!(" <code>{}</code>", escape(&site.snippet))
+    }
+}
+
+/// Line `n` of a file, with the parts in `sites` highlighted and the starts of `sites` anchored.
+fn render_line(n: u64, line: &str, sites: &[&Site]) -> String {
+    let sites: Vec<(&Site, &Span)> = sites.iter()
+        .filter_map(|site| site.span.as_ref().map(|span| (*site, span)))
+        .filter(|&(_, span)| span.lo_line() <= n && n <= span.hi_line())
+        .collect();
+    let mut out = format!("<span id=\"L{}\"><span class=\"ln\">{}</span>", n, n);
+    // The classes and innermost site of the current run of characters
+    let mut current: Option<(BTreeSet<Class>, usize)> = None;
+    let mut len = 0;
+    for (i, c) in line.chars().enumerate() {
+        let col = i as u64 + 1;
+        len = col;
+        let covering: Vec<&Site> = sites.iter()
+            .filter(|&&(_, span)| span.contains(n, col))
+            .map(|&(site, _)| site)
+            .collect();
+        let starting: Vec<&Site> = sites.iter()
+            .filter(|&&(_, span)| span.lo_line() == n && span.lo_col() == col)
+            .map(|&(site, _)| site)
+            .collect();
+        let run = covering.last().map(|innermost| {
+            (covering.iter().map(|site| site.class).collect::<BTreeSet<_>>(), innermost.id)
+        });
+        if run != current || !starting.is_empty() {
+            if current.is_some() {
+                out.push_str("</span>");
+            }
+            for site in starting {
+                write!(out, "<span id=\"site-{}\"></span>", site.id).unwrap();
+            }
+            if let Some((ref classes, id)) = run {
+                let names: Vec<&str> = classes.iter().map(|class| class.name()).collect();
+                let innermost = covering.iter().find(|site| site.id == id).unwrap();
+                write!(out, "<span class=\"{}\" title=\"{}\">", names.join(" "),
+                       escape(&innermost.describe())).unwrap();
+            }
+            current = run;
+        }
+        match c {
+            '\t' => out.push_str("    "),
+            c => out.push_str(&escape(&c.to_string())),
+        }
+    }
+    if current.is_some() {
+        out.push_str("</span>");
+    }
+    // Sites which start past the end of the line, so there is still somewhere to link to
+    for &(site, span) in &sites {
+        if span.lo_line() == n && span.lo_col() > len {
+            write!(out, "<span id=\"site-{}\"></span>", site.id).unwrap();
+        }
+    }
+    out.push_str("</span>\n");
+    out
+}
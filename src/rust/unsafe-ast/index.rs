@@ -0,0 +1,309 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// An index of a UAST file by crate name and version, for looking crates up without a full scan
+//
+// The index of `json.out` is the sidecar file `json.out.idx`, which is text:
+//
+//     unsafe-ast-index	2	json	<length of json.out in bytes>	<its mtime>	<its edge hash>
+//     <crate name>	<version, or "-">	<offset>	<length>
+//     ...
+//
+// with one line per UAST, in file order, giving where its line starts, in bytes, and how long it
+// is (without the newline). The index of a binary UAST file (see `binary.rs`) is the same, but
+// says "binary" and locates records, lengths included. The version is the package version
+// `emit-ast` recorded (`meta.version`), which UASTs from before the UAST was versioned lack.
+//
+// The mtime is `<seconds>.<nanoseconds>` since the Unix epoch (or "-" where there are no mtimes),
+// and the edge hash is the FNV-1a hash of the first and last 4 KiB of the data file, in hex. If any
+// of the three has changed since the data file was indexed, the index is stale, and reading it
+// fails. A change which keeps all three (rewriting the middle of the file in place, and then
+// resetting its mtime) isn't noticed, but then loading a UAST still checks that it is of the crate
+// the index says it is.
+
+use rustc_serialize::json::Json;
+
+use std::ffi::OsString;
+use std::fs::{self,File};
+use std::io::{self,BufRead,BufReader,Read,Seek,SeekFrom,Write};
+use std::path::{Path,PathBuf};
+use std::time::UNIX_EPOCH;
+
+use atomic;
+use baseline::fnv1a;
+use binary;
+use uast::Crate;
+
+const MAGIC: &'static str = "unsafe-ast-index";
+const VERSION: u32 = 2;
+
+/// How much of each end of the data file the edge hash covers.
+const EDGE: u64 = 4096;
+
+/// Where one UAST is in the data file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Entry {
+    name: String,
+    version: Option<String>,
+    offset: u64,
+    length: u64,
+}
+
+impl Entry {
+    pub fn name(&self) -> &str { &self.name }
+    pub fn version(&self) -> Option<&str> { self.version.as_ref().map(|v| &v[..]) }
+    pub fn offset(&self) -> u64 { self.offset }
+    pub fn length(&self) -> u64 { self.length }
+}
+
+/// What the index records of the data file, to tell whether it has changed since.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Stamp {
+    length: u64,
+    modified: String,
+    edge_hash: u64,
+}
+
+impl Stamp {
+    fn of(data: &Path) -> io::Result<Stamp> {
+        let metadata = try!(fs::metadata(data));
+        let modified = metadata.modified().ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or("-".to_string(), |d| format!("{}.{:09}", d.as_secs(), d.subsec_nanos()));
+        let length = metadata.len();
+        let mut file = try!(File::open(data));
+        let mut edges = vec![];
+        if length > 2 * EDGE {
+            try!((&mut file).take(EDGE).read_to_end(&mut edges));
+            try!(file.seek(SeekFrom::End(-(EDGE as i64))));
+        }
+        try!(file.take(2 * EDGE).read_to_end(&mut edges));
+        Ok(Stamp { length: length, modified: modified, edge_hash: fnv1a(&edges) })
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Index {
+    binary: bool,
+    data: Stamp,
+    entries: Vec<Entry>,
+}
+
+fn invalid<S: Into<String>>(msg: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Checks that `entry` is the entry of the UAST of the crate `name`.
+fn check_name(entry: &Entry, name: Option<&str>) -> io::Result<()> {
+    if name == Some(&entry.name[..]) {
+        Ok(())
+    } else {
+        Err(invalid(format!("Found {} where the index has {}, so the index is stale",
+                            name.unwrap_or("a UAST without a name"), entry.name)))
+    }
+}
+
+impl Index {
+    /// The index file for the data file `data`.
+    pub fn path(data: &Path) -> PathBuf {
+        let mut path = OsString::from(data.as_os_str());
+        path.push(".idx");
+        PathBuf::from(path)
+    }
+
+    /// Indexes the data file `data`, of JSON lines or binary UASTs.
+    pub fn build(data: &Path) -> io::Result<Index> {
+        let mut input = BufReader::new(try!(File::open(data)));
+        let binary = binary::is_binary(try!(input.fill_buf()));
+        let entries = if binary {
+            try!(Index::entries_of_binary(input))
+        } else {
+            try!(Index::entries_of_lines(input))
+        };
+        Ok(Index {
+            binary: binary,
+            data: try!(Stamp::of(data)),
+            entries: entries,
+        })
+    }
+
+    fn entries_of_lines<R: BufRead>(mut input: R) -> io::Result<Vec<Entry>> {
+        let mut entries = vec![];
+        let mut offset = 0;
+        let mut line = vec![];
+        for n in 1.. {
+            line.clear();
+            let read = try!(input.read_until(b'\n', &mut line)) as u64;
+            if read == 0 {
+                break;
+            }
+            let text = try!(String::from_utf8(line.clone())
+                                .map_err(|_| invalid(format!("Line {} is not UTF-8", n))));
+            let text = text.trim_right_matches(|c| c == '\n' || c == '\r');
+            if !text.trim().is_empty() {
+                let json = try!(Json::from_str(text)
+                                    .map_err(|e| invalid(format!("Line {}: {}", n, e))));
+                let name = try!(json.find("name").and_then(Json::as_string)
+                                    .ok_or(invalid(format!("Line {} has no crate name", n))));
+                let version = json.find_path(&["meta", "version"]).and_then(Json::as_string);
+                entries.push(Entry {
+                    name: name.to_string(),
+                    version: version.map(|v| v.to_string()),
+                    offset: offset,
+                    length: text.len() as u64,
+                });
+            }
+            offset += read;
+        }
+        Ok(entries)
+    }
+
+    fn entries_of_binary<R: Read>(input: R) -> io::Result<Vec<Entry>> {
+        let mut records = try!(binary::records(input).map_err(|e| invalid(e.to_string())));
+        let mut entries = vec![];
+        let mut n = 1;
+        loop {
+            let offset = records.offset();
+            let record = match records.next() {
+                Some(record) => try!(record.map_err(|e| invalid(e.to_string()))),
+                None => break,
+            };
+            let krate = try!(binary::decode(&record)
+                                 .map_err(|e| invalid(format!("Record {}: {}", n, e))));
+            entries.push(Entry {
+                name: krate.name().to_string(),
+                version: krate.meta().version().map(|v| v.to_string()),
+                offset: offset,
+                length: records.offset() - offset,
+            });
+            n += 1;
+        }
+        Ok(entries)
+    }
+
+    /// Writes the index for the data file `data`, moving it into place only once it is complete.
+    pub fn write(&self, data: &Path) -> io::Result<()> {
+        atomic::write_atomically(&Index::path(data), |out| {
+            try!(writeln!(out, "{}\t{}\t{}\t{}\t{}\t{:016x}", MAGIC, VERSION,
+                          if self.binary { "binary" } else { "json" }, self.data.length,
+                          self.data.modified, self.data.edge_hash));
+            for entry in &self.entries {
+                try!(writeln!(out, "{}\t{}\t{}\t{}", entry.name,
+                              entry.version.as_ref().map_or("-", |v| &v[..]),
+                              entry.offset, entry.length));
+            }
+            Ok(())
+        })
+    }
+
+    /// Reads the index of the data file `data`, checking that it isn't stale.
+    pub fn read(data: &Path) -> io::Result<Index> {
+        let path = Index::path(data);
+        let input = BufReader::new(try!(File::open(&path)));
+        let mut lines = input.lines();
+        let header = match lines.next() {
+            Some(header) => try!(header),
+            None => return Err(invalid(format!("{} is empty", path.display()))),
+        };
+        let header: Vec<&str> = header.split('\t').collect();
+        let not_an_index = || invalid(format!("{} is not a UAST index", path.display()));
+        if header.len() < 2 || header[0] != MAGIC {
+            return Err(not_an_index());
+        }
+        if header[1] != VERSION.to_string() {
+            return Err(invalid(format!("{} is from another version of `analyze`, and needs \
+                                        rebuilding", path.display())));
+        }
+        let indexed = match (header.get(3).map(|l| l.parse()),
+                             header.get(5).map(|h| u64::from_str_radix(h, 16))) {
+            (Some(Ok(length)), Some(Ok(edge_hash))) if header.len() == 6 => Stamp {
+                length: length,
+                modified: header[4].to_string(),
+                edge_hash: edge_hash,
+            },
+            _ => return Err(not_an_index()),
+        };
+        let stamp = try!(Stamp::of(data));
+        if stamp != indexed {
+            return Err(invalid(format!("{} is stale, since {} has changed",
+                                       path.display(), data.display())));
+        }
+        let mut entries = vec![];
+        for line in lines {
+            let line = try!(line);
+            let fields: Vec<&str> = line.split('\t').collect();
+            let entry = match (fields.get(0), fields.get(1), fields.get(2), fields.get(3)) {
+                (Some(name), Some(version), Some(offset), Some(length)) => {
+                    match (offset.parse(), length.parse()) {
+                        (Ok(offset), Ok(length)) => Some(Entry {
+                            name: name.to_string(),
+                            version: if *version == "-" { None } else { Some(version.to_string()) },
+                            offset: offset,
+                            length: length,
+                        }),
+                        _ => None,
+                    }
+                },
+                _ => None,
+            };
+            entries.push(try!(entry.ok_or(invalid(format!("Bad line in {}: {}",
+                                                          path.display(), line)))));
+        }
+        Ok(Index { binary: header[2] == "binary", data: stamp, entries: entries })
+    }
+
+    /// Whether the data file is a binary UAST file, rather than JSON lines.
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// The UASTs of the crates named `name`, of any version if `version` is `None`, in file
+    /// order.
+    pub fn find(&self, name: &str, version: Option<&str>) -> Vec<&Entry> {
+        self.entries.iter()
+            .filter(|entry| entry.name == name && (version.is_none() || entry.version() == version))
+            .collect()
+    }
+
+    /// The bytes of a UAST in the data file: its line, or its record (with the length).
+    pub fn raw<R: Read + Seek>(&self, data: &mut R, entry: &Entry) -> io::Result<Vec<u8>> {
+        try!(data.seek(SeekFrom::Start(entry.offset)));
+        let mut raw = vec![];
+        try!(data.take(entry.length).read_to_end(&mut raw));
+        if (raw.len() as u64) < entry.length {
+            return Err(invalid("The data file ended early"));
+        }
+        Ok(raw)
+    }
+
+    /// The line of a UAST in a data file of JSON lines, checked to be that of the crate the entry
+    /// names.
+    pub fn line<R: Read + Seek>(&self, data: &mut R, entry: &Entry) -> io::Result<String> {
+        let raw = try!(self.raw(data, entry));
+        let line = try!(String::from_utf8(raw).map_err(|_| invalid("The UAST is not UTF-8")));
+        {
+            let json = try!(Json::from_str(&line).map_err(|e| invalid(e.to_string())));
+            try!(check_name(entry, json.find("name").and_then(Json::as_string)));
+        }
+        Ok(line)
+    }
+
+    /// Reads and decodes a UAST from the data file, checking that it is of the crate the entry
+    /// names.
+    pub fn load<R: Read + Seek>(&self, data: &mut R, entry: &Entry) -> io::Result<Crate> {
+        if !self.binary {
+            let line = try!(self.line(data, entry));
+            return ::decode(&line).map_err(|e| invalid(e.to_string()));
+        }
+        let raw = try!(self.raw(data, entry));
+        let krate = match binary::read_record(&mut &raw[..]) {
+            Ok(Some(record)) => try!(binary::decode(&record).map_err(|e| invalid(e.to_string()))),
+            Ok(None) => return Err(invalid("Empty record")),
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+        try!(check_name(entry, Some(krate.name())));
+        Ok(krate)
+    }
+}
@@ -0,0 +1,269 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Printing values exactly as `jq -c` (1.6) prints them
+//
+// The analyses in `analysis.rs` stand in for those in `data-analysis/unsafe.jq`, so their output
+// has to match jq's byte for byte. `rustc_serialize` already escapes strings the way jq does, and
+// writes the fields of structs in the order jq keeps them in, but it prints numbers differently
+// (`1.0` rather than `1`), and sorts the keys of `Json::Object`s. Objects and numbers are built
+// with the functions here instead.
+//
+// jq also prints the parts of a UAST it selects just as it read them, whereas decoding a UAST
+// upgrades it (see `encoding::upgrade`). `Printer` prints the parts of a decoded UAST from the
+// `Value` it was decoded from, which keeps its keys in the order they were read in.
+
+use rustc_serialize::Encodable;
+use rustc_serialize::json::{self,ErrorCode,JsonEvent,Parser,ParserError,StackElement};
+
+use uast::{Block,Crate,Indexed,UASTNode};
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Prints a number as jq does: the shortest decimal which reads back as `x`, in exponential
+/// notation when very large or small, and without a fractional part when it is whole.
+pub fn number(x: f64) -> String {
+    if x.is_nan() {
+        return "null".to_string();
+    }
+    if x.is_infinite() {
+        // jq clamps infinities to the largest double
+        return number(if x > 0.0 { ::std::f64::MAX } else { ::std::f64::MIN });
+    }
+    // The shortest digits which read back as `x`, but of those, jq picks the closest to `x`, which
+    // is what rounding `x` to that many digits gives. e.g. "-3.3333333333333335e-5"
+    let shortest = format!("{:e}", x);
+    let precision = shortest.split('e').next().unwrap_or("").chars()
+        .filter(|c| c.is_digit(10))
+        .count();
+    let exponential = format!("{:.*e}", precision.saturating_sub(1), x);
+    let mut parts = exponential.split('e');
+    let mantissa = parts.next().unwrap_or("0");
+    let exponent: i64 = parts.next().and_then(|e| e.parse().ok()).unwrap_or(0);
+    let digits: String = mantissa.chars().filter(|c| c.is_digit(10)).collect();
+    let ndigits = digits.len() as i64;
+    // The position of the decimal point, relative to the start of `digits`
+    let point = exponent + 1;
+
+    let mut out = String::new();
+    if mantissa.starts_with('-') {
+        out.push('-');
+    }
+    if point <= -4 || point > ndigits + 15 {
+        out.push_str(&digits[..1]);
+        if ndigits > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push_str(&format!("e{}{:02}", if exponent < 0 { '-' } else { '+' }, exponent.abs()));
+    } else if point <= 0 {
+        out.push_str("0.");
+        for _ in 0..-point {
+            out.push('0');
+        }
+        out.push_str(&digits);
+    } else if point >= ndigits {
+        out.push_str(&digits);
+        for _ in 0..(point - ndigits) {
+            out.push('0');
+        }
+    } else {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    }
+    out
+}
+
+/// Prints a string as a JSON string.
+pub fn string(s: &str) -> String {
+    encode(&s)
+}
+
+/// Prints part of a UAST, in the order its fields are declared in (and written by `emit-ast`).
+pub fn encode<T: Encodable>(value: &T) -> String {
+    json::encode(value).expect("UASTs should always encode as JSON")
+}
+
+/// Prints an array of already printed values.
+pub fn array(items: &[String]) -> String {
+    format!("[{}]", items.join(","))
+}
+
+/// Prints an object of already printed values, keeping the keys in the order given.
+pub fn object(pairs: &[(&str, String)]) -> String {
+    let fields: Vec<String> = pairs.iter()
+        .map(|&(key, ref value)| format!("{}:{}", string(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// A JSON value as jq holds it: numbers are doubles, and objects keep their keys in the order they
+/// were read in.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// The field `key` of an object.
+    pub fn find(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref fields) => fields.iter().find(|f| f.0 == key).map(|f| &f.1),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Prints the value as `jq -c` does.
+    pub fn print(&self) -> String {
+        match *self {
+            Value::Null => "null".to_string(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Number(x) => number(x),
+            Value::String(ref s) => string(s),
+            Value::Array(ref items) => {
+                let items: Vec<String> = items.iter().map(Value::print).collect();
+                array(&items)
+            },
+            Value::Object(ref fields) => {
+                let fields: Vec<(&str, String)> = fields.iter()
+                    .map(|&(ref key, ref value)| (&key[..], value.print()))
+                    .collect();
+                object(&fields)
+            },
+        }
+    }
+}
+
+/// Parses one JSON value, as jq reads it.
+pub fn parse(s: &str) -> Result<Value, ParserError> {
+    let mut parser = Parser::new(s.chars());
+    let first = parser.next();
+    let value = try!(parse_value(&mut parser, first));
+    match parser.next() {
+        None => Ok(value),
+        Some(JsonEvent::Error(e)) => Err(e),
+        Some(_) => Err(ParserError::SyntaxError(ErrorCode::TrailingCharacters, 0, 0)),
+    }
+}
+
+/// Parses the value which starts with `event`.
+fn parse_value<T>(parser: &mut Parser<T>, event: Option<JsonEvent>) -> Result<Value, ParserError>
+    where T: Iterator<Item=char>
+{
+    match event {
+        Some(JsonEvent::NullValue) => Ok(Value::Null),
+        Some(JsonEvent::BooleanValue(b)) => Ok(Value::Boolean(b)),
+        Some(JsonEvent::I64Value(n)) => Ok(Value::Number(n as f64)),
+        Some(JsonEvent::U64Value(n)) => Ok(Value::Number(n as f64)),
+        Some(JsonEvent::F64Value(x)) => Ok(Value::Number(x)),
+        Some(JsonEvent::StringValue(s)) => Ok(Value::String(s)),
+        Some(JsonEvent::ArrayStart) => {
+            let mut items = vec![];
+            loop {
+                match parser.next() {
+                    Some(JsonEvent::ArrayEnd) => return Ok(Value::Array(items)),
+                    event => items.push(try!(parse_value(parser, event))),
+                }
+            }
+        },
+        Some(JsonEvent::ObjectStart) => {
+            let mut fields: Vec<(String, Value)> = vec![];
+            loop {
+                let event = parser.next();
+                if let Some(JsonEvent::ObjectEnd) = event {
+                    return Ok(Value::Object(fields));
+                }
+                let key = match parser.stack().top() {
+                    Some(StackElement::Key(key)) => key.to_string(),
+                    _ => String::new(),
+                };
+                let value = try!(parse_value(parser, event));
+                // Like jq, a repeated key keeps its first place, but takes its last value
+                if let Some(field) = fields.iter_mut().find(|f| f.0 == key) {
+                    field.1 = value;
+                    continue;
+                }
+                fields.push((key, value));
+            }
+        },
+        Some(JsonEvent::Error(e)) => Err(e),
+        Some(JsonEvent::ArrayEnd) |
+        Some(JsonEvent::ObjectEnd) => {
+            Err(ParserError::SyntaxError(ErrorCode::InvalidSyntax, 0, 0))
+        },
+        None => Err(ParserError::SyntaxError(ErrorCode::EOFWhileParsingValue, 0, 0)),
+    }
+}
+
+/// Prints the blocks and nodes of a decoded UAST as jq would: as they were read, rather than as
+/// they were upgraded when decoded. Those it has no `Value` for are encoded afresh.
+pub struct Printer<'a> {
+    blocks: HashMap<*const Block, &'a Value>,
+    nodes: HashMap<*const Indexed<UASTNode>, &'a Value>,
+    // The keys point into a `Crate`, which must not move
+    krate: PhantomData<&'a Crate>,
+}
+
+impl<'a> Printer<'a> {
+    /// A printer which encodes everything afresh, for UASTs which were not read as JSON.
+    pub fn new() -> Printer<'a> {
+        Printer { blocks: HashMap::new(), nodes: HashMap::new(), krate: PhantomData }
+    }
+
+    /// A printer for `krate`, which was decoded from `read`.
+    pub fn as_read(krate: &'a Crate, read: &'a Value) -> Printer<'a> {
+        let mut printer = Printer::new();
+        let functions = read.find("functions").and_then(Value::as_array).unwrap_or(&[]);
+        for (function, read) in krate.functions().iter().zip(functions) {
+            if let Some(read) = read.find("block") {
+                printer.add_block(function.block(), read);
+            }
+        }
+        printer
+    }
+
+    fn add_block(&mut self, block: &'a Block, read: &'a Value) {
+        self.blocks.insert(block, read);
+        let contents = read.find("contents").and_then(Value::as_array).unwrap_or(&[]);
+        for (node, read) in block.contents().iter().zip(contents) {
+            self.nodes.insert(node, read);
+            if let Some(inner) = node.item().block() {
+                // `{"variant": .., "fields": [<block>]}`, or `{"kind": .., "block": <block>}`
+                let item = read.find("item");
+                let inner_read = item.and_then(|item| item.find("fields"))
+                    .and_then(Value::as_array)
+                    .and_then(|fields| fields.first())
+                    .or_else(|| item.and_then(|item| item.find("block")));
+                if let Some(inner_read) = inner_read {
+                    self.add_block(inner, inner_read);
+                }
+            }
+        }
+    }
+
+    pub fn block(&self, block: &Block) -> String {
+        match self.blocks.get(&(block as *const Block)) {
+            Some(read) => read.print(),
+            None => encode(block),
+        }
+    }
+
+    pub fn node(&self, node: &Indexed<UASTNode>) -> String {
+        match self.nodes.get(&(node as *const Indexed<UASTNode>)) {
+            Some(read) => read.print(),
+            None => encode(node),
+        }
+    }
+}
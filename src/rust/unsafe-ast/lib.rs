@@ -0,0 +1,45 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// The Unsafe AST data model, for tools which read the UASTs `emit-ast` writes
+//
+// Unlike `emit-ast`, this does not use the compiler's internals, so it builds on stable Rust. It
+// must also keep building on the nightly `emit-ast` is pinned to, which links it, so it can only
+// use what the standard library had then (late 2016): no `?`, and no newer APIs like
+// `f64::to_bits`.
+
+extern crate rustc_serialize;
+
+pub mod analysis;
+pub mod atomic;
+pub mod baseline;
+pub mod binary;
+pub mod diff;
+pub mod encoding;
+pub mod html;
+pub mod index;
+pub mod jq;
+pub mod outputs;
+pub mod sarif;
+pub mod schema;
+pub mod span;
+pub mod summary;
+mod uast;
+pub mod visit;
+
+pub use uast::*;
+
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{self,Json};
+
+/// Decodes one UAST, as written by `emit-ast` (one per line of `json.out`). Both encodings are
+/// accepted, and records from before the UAST was versioned are upgraded, see
+/// `encoding::upgrade`.
+pub fn decode(s: &str) -> json::DecodeResult<Crate> {
+    let json = try!(Json::from_str(s).map_err(json::DecoderError::ParseError));
+    decode_json(json)
+}
+
+/// Like `decode`, for an already parsed UAST.
+pub fn decode_json(json: Json) -> json::DecodeResult<Crate> {
+    let json = encoding::upgrade(encoding::untag(json));
+    Decodable::decode(&mut json::Decoder::new(json))
+}
@@ -0,0 +1,214 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// The files written by `run_jq_programs_if_needed` in `data-analysis/initial-analysis.sh`
+//
+// Each of those files is made by a jq program, run over `json.out` or over another of the files.
+// Either way, it comes down to some lines for each UAST, which `Output::lines` computes straight
+// from the UAST, so that all the files can be written in one pass over `json.out`. The lines are
+// just what `jq -c -r` prints, and the blocks and nodes in them are printed as they were read (see
+// `jq::Printer`).
+
+use analysis;
+use jq;
+use uast::{Block,Crate,Indexed,UASTNode};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Output {
+    UnsafeBlockSizes,
+    UnsafeBlockRelSizes,
+    UnsafeBlockRequirement,
+    UnsafeBlockRelSizeByCrate,
+    UnsafeBlockRequirementByCrate,
+    UnsafeBlockRelSize,
+    UnsafeBlockRequirementFlat,
+    UnsafeBlockRelSizeAndReq,
+    UnsafeUsesByCrate,
+    UnsafeUses,
+    UnsafeContexts,
+    Closures,
+    ClosureWithUnsafeUsesCounts,
+    InnerBlocks,
+    InnerBlocksFlat,
+    Fns,
+    UnsafeBlockCounts,
+    UnsafeFnCounts,
+    UnsafeContextCounts,
+}
+
+/// All the outputs, in the order `initial-analysis.sh` lists them.
+pub const OUTPUTS: &'static [Output] = &[
+    Output::UnsafeBlockSizes,
+    Output::UnsafeBlockRelSizes,
+    Output::UnsafeBlockRequirement,
+    Output::UnsafeBlockRelSizeByCrate,
+    Output::UnsafeBlockRequirementByCrate,
+    Output::UnsafeBlockRelSize,
+    Output::UnsafeBlockRequirementFlat,
+    Output::UnsafeBlockRelSizeAndReq,
+    Output::UnsafeUsesByCrate,
+    Output::UnsafeUses,
+    Output::UnsafeContexts,
+    Output::Closures,
+    Output::ClosureWithUnsafeUsesCounts,
+    Output::InnerBlocks,
+    Output::InnerBlocksFlat,
+    Output::Fns,
+    Output::UnsafeBlockCounts,
+    Output::UnsafeFnCounts,
+    Output::UnsafeContextCounts,
+];
+
+impl Output {
+    pub fn file_name(&self) -> &'static str {
+        match *self {
+            Output::UnsafeBlockSizes => "unsafe_block_sizes.txt",
+            Output::UnsafeBlockRelSizes => "unsafe_block_rel_sizes.json",
+            Output::UnsafeBlockRequirement => "unsafe_block_requirement.json",
+            Output::UnsafeBlockRelSizeByCrate => "unsafe_block_rel_size_by_crate.txt",
+            Output::UnsafeBlockRequirementByCrate => "unsafe_block_requirement_by_crate.txt",
+            Output::UnsafeBlockRelSize => "unsafe_block_rel_size.txt",
+            Output::UnsafeBlockRequirementFlat => "unsafe_block_requirement.txt",
+            Output::UnsafeBlockRelSizeAndReq => "unsafe_block_rel_size_and_req.txt",
+            Output::UnsafeUsesByCrate => "unsafe_uses_by_crate.json",
+            Output::UnsafeUses => "unsafe_uses.json",
+            Output::UnsafeContexts => "unsafe_contexts.json",
+            Output::Closures => "closures.json",
+            Output::ClosureWithUnsafeUsesCounts => "closure_with_unsafe_uses_counts.txt",
+            Output::InnerBlocks => "inner_blocks.json",
+            Output::InnerBlocksFlat => "inner_blocks_flat.json",
+            Output::Fns => "fns.json",
+            Output::UnsafeBlockCounts => "unsafe_block_counts.txt",
+            Output::UnsafeFnCounts => "unsafe_fn_counts.txt",
+            Output::UnsafeContextCounts => "unsafe_context_counts.txt",
+        }
+    }
+
+    pub fn from_file_name(name: &str) -> Option<Output> {
+        OUTPUTS.iter().find(|output| output.file_name() == name).cloned()
+    }
+
+    /// The lines of the file which come from `krate`, with its blocks and nodes printed by
+    /// `printer`.
+    pub fn lines(&self, krate: &Crate, printer: &jq::Printer) -> Vec<String> {
+        let name = jq::string(krate.name());
+        match *self {
+            Output::UnsafeBlockSizes => {
+                analysis::unsafe_blocks(krate).into_iter()
+                    .map(|block| jq::number(analysis::block_net_size(block) as f64))
+                    .collect()
+            },
+            Output::UnsafeBlockRelSizes => {
+                let sizes = numbers(&analysis::unsafe_blocks_rel_sizes(krate));
+                vec![jq::object(&[("name", name), ("rel_sizes", jq::array(&sizes))])]
+            },
+            Output::UnsafeBlockRequirement => {
+                let used = numbers(&analysis::unsafe_blocks_requirement(krate));
+                vec![jq::object(&[("name", name), ("used", jq::array(&used))])]
+            },
+            Output::UnsafeBlockRelSizeByCrate => {
+                mean(&analysis::unsafe_blocks_rel_sizes(krate))
+            },
+            Output::UnsafeBlockRequirementByCrate => {
+                mean(&analysis::unsafe_blocks_requirement(krate))
+            },
+            Output::UnsafeBlockRelSize => numbers(&analysis::unsafe_blocks_rel_sizes(krate)),
+            Output::UnsafeBlockRequirementFlat => {
+                numbers(&analysis::unsafe_blocks_requirement(krate))
+            },
+            Output::UnsafeBlockRelSizeAndReq => {
+                analysis::unsafe_blocks_with_functions(krate).into_iter()
+                    .map(|(parent, child)| {
+                        // @tsv
+                        format!("{}\t{}",
+                                jq::number(analysis::block_relative_size(parent, child)),
+                                jq::number(analysis::block_requirement(child)))
+                    })
+                    .collect()
+            },
+            Output::UnsafeUsesByCrate => {
+                let uses = nodes(printer, &analysis::all_unsafe_uses(krate));
+                vec![jq::object(&[("name", name), ("uses", jq::array(&uses))])]
+            },
+            Output::UnsafeUses => nodes(printer, &analysis::all_unsafe_uses(krate)),
+            Output::UnsafeContexts => {
+                let contexts = blocks(printer, &unsafe_contexts(krate));
+                vec![jq::object(&[("name", name), ("contexts", jq::array(&contexts))])]
+            },
+            Output::Closures => {
+                let closures = blocks(printer,
+                                      &analysis::closure_blocks_with_unsafe_uses(krate));
+                vec![jq::object(&[("name", name), ("closures", jq::array(&closures))])]
+            },
+            Output::ClosureWithUnsafeUsesCounts => {
+                count(analysis::closure_blocks_with_unsafe_uses(krate).len())
+            },
+            Output::InnerBlocks => {
+                vec![jq::object(&[("name", name), ("blocks", jq::array(&inner_blocks(krate)))])]
+            },
+            Output::InnerBlocksFlat => inner_blocks(krate),
+            Output::Fns => {
+                let functions: Vec<String> = krate.functions().iter()
+                    .map(|f| jq::object(&[("unsaf", jq::encode(&f.unsaf())),
+                                          ("name", jq::string(f.name()))]))
+                    .collect();
+                vec![jq::object(&[("name", name), ("functions", jq::array(&functions))])]
+            },
+            // The unsafe ones of `inner_blocks`
+            Output::UnsafeBlockCounts => count(analysis::unsafe_blocks(krate).len()),
+            Output::UnsafeFnCounts => {
+                count(krate.functions().iter().filter(|f| f.unsaf()).count())
+            },
+            Output::UnsafeContextCounts => count(unsafe_contexts(krate).len()),
+        }
+    }
+}
+
+/// The bodies of the unsafe functions, and then all the unsafe blocks.
+fn unsafe_contexts(krate: &Crate) -> Vec<&Block> {
+    let mut contexts: Vec<_> = krate.functions().iter()
+        .filter(|f| f.unsaf())
+        .map(|f| f.block())
+        .collect();
+    contexts.extend(analysis::unsafe_blocks_with_functions(krate).into_iter()
+                        .map(|(_, child)| child));
+    contexts
+}
+
+/// A summary of each inner (not function body) block: `{size, unsaf, macro_origin}`.
+fn inner_blocks(krate: &Crate) -> Vec<String> {
+    let mut summaries = vec![];
+    for function in krate.functions() {
+        for block in analysis::blocks(function.block()) {
+            for node in block.contents() {
+                if let UASTNode::InnerBlock(ref inner) = *node.item() {
+                    summaries.push(jq::object(&[
+                        ("size", jq::number(inner.size() as f64)),
+                        ("unsaf", jq::encode(&inner.unsaf())),
+                        ("macro_origin", jq::encode(&node.macro_origin())),
+                    ]));
+                }
+            }
+        }
+    }
+    summaries
+}
+
+fn numbers(xs: &[f64]) -> Vec<String> {
+    xs.iter().map(|&x| jq::number(x)).collect()
+}
+
+fn nodes(printer: &jq::Printer, nodes: &[&Indexed<UASTNode>]) -> Vec<String> {
+    nodes.iter().map(|node| printer.node(node)).collect()
+}
+
+fn blocks(printer: &jq::Printer, blocks: &[&Block]) -> Vec<String> {
+    blocks.iter().map(|block| printer.block(block)).collect()
+}
+
+fn count(n: usize) -> Vec<String> {
+    vec![jq::number(n as f64)]
+}
+
+/// `select(length > 0) | mean`
+fn mean(xs: &[f64]) -> Vec<String> {
+    if xs.is_empty() { vec![] } else { vec![jq::number(analysis::mean(xs))] }
+}
@@ -0,0 +1,175 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// SARIF 2.1.0 logs of the unsafe sites of crates, for code-scanning dashboards
+//
+// Each unsafe function, unsafe block and unsafe use (the sites of `baseline::sites`) is a result,
+// whose rule is the kind of site: `UnsafeFn`, `UnsafeBlock`, or the `UASTNode` kind of the use.
+// Results are notes rather than warnings, since unsafe code isn't a defect, and carry the site's
+// baseline fingerprint as a partial fingerprint, so that dashboards follow a site as code moves.
+//
+// Regions are in the columns of the spans, which count characters, so the run says its
+// `columnKind` is `unicodeCodePoints`. Relative paths (the usual, for a crate's own source) are
+// relative to `%SRCROOT%`; sites in the expansions of external macros have no physical location.
+
+use rustc_serialize::json::{Json,Object};
+
+use baseline::{self,Site};
+use span::Span;
+use uast::Crate;
+
+pub const VERSION: &'static str = "2.1.0";
+pub const SCHEMA: &'static str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The base which relative paths are relative to.
+pub const SRCROOT: &'static str = "%SRCROOT%";
+
+/// The id of each rule, and its description.
+pub const RULES: &'static [(&'static str, &'static str)] = &[
+    ("UnsafeFn", "An unsafe function"),
+    ("UnsafeBlock", "An unsafe block"),
+    ("Deref", "A dereference of a raw pointer"),
+    ("MutStatic", "A use of a mutable static"),
+    ("ExternStatic", "A use of an extern static"),
+    ("InlineASM", "Inline assembly"),
+    ("UnionFieldRead", "A read of a union field"),
+    ("UnionFieldWrite", "A write to a union field"),
+    ("Call", "A call of an unsafe function"),
+];
+
+fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect::<Object>())
+}
+
+fn text(s: &str) -> Json {
+    object(vec![("text", string(s))])
+}
+
+/// `path` as a URI reference, and whether it is relative to `SRCROOT`.
+pub fn uri(path: &str) -> (String, bool) {
+    let path = path.replace('\\', "/");
+    let mut encoded = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'.' | b'_' | b'~' | b'/' =>
+                encoded.push(byte as char),
+            b':' if encoded.len() == 1 => encoded.push(':'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    if encoded.starts_with('/') {
+        (format!("file://{}", encoded), false)
+    } else if path.as_bytes().get(1) == Some(&b':') {
+        (format!("file:///{}", encoded), false)
+    } else {
+        (encoded, true)
+    }
+}
+
+/// Whether `uri` is an absolute URI: it has a scheme, like `file:` or `https:`. A drive letter, as
+/// in `C:/src`, isn't taken for a scheme.
+pub fn is_absolute_uri(uri: &str) -> bool {
+    fn letter(c: char) -> bool {
+        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z')
+    }
+    let scheme = match uri.find(':') {
+        Some(end) => &uri[..end],
+        None => return false,
+    };
+    scheme.len() >= 2 && scheme.starts_with(letter) &&
+        scheme.chars().all(|c| letter(c) || (c >= '0' && c <= '9') || "+-.".contains(c))
+}
+
+fn rule(id: &str, description: &str) -> Json {
+    object(vec![
+        ("id", string(id)),
+        ("name", string(id)),
+        ("shortDescription", text(description)),
+        ("defaultConfiguration", object(vec![("level", string("note"))])),
+    ])
+}
+
+fn location(site: &Site) -> Json {
+    let mut fields = vec![];
+    if let Some(span) = Span::of(site.span(), site.span_info()).and_then(|span| {
+        if span.is_real() { Some(span) } else { None }
+    }) {
+        let (uri, relative) = uri(span.file());
+        let mut artifact = vec![("uri", Json::String(uri))];
+        if relative {
+            artifact.push(("uriBaseId", string(SRCROOT)));
+        }
+        let region = object(vec![
+            ("startLine", Json::U64(span.lo_line())),
+            ("startColumn", Json::U64(span.lo_col())),
+            ("endLine", Json::U64(span.hi_line())),
+            ("endColumn", Json::U64(span.hi_col())),
+        ]);
+        fields.push(("physicalLocation", object(vec![("artifactLocation", object(artifact)),
+                                                     ("region", region)])));
+    }
+    let name = site.function().rsplit("::").next().unwrap_or(site.function());
+    fields.push(("logicalLocations", Json::Array(vec![object(vec![
+        ("name", string(name)),
+        ("fullyQualifiedName", Json::String(format!("{}::{}", site.krate(), site.function()))),
+        ("kind", string("function")),
+    ])])));
+    object(fields)
+}
+
+fn result(site: &Site) -> Json {
+    let index = RULES.iter().position(|&(id, _)| id == site.kind())
+        .expect("Every kind of site should have a rule");
+    let message = match site.kind() {
+        "UnsafeFn" => format!("unsafe fn {}", site.function()),
+        "UnsafeBlock" => format!("unsafe block in {}", site.function()),
+        kind => format!("{} in {}: `{}`", kind, site.function(), site.snippet()),
+    };
+    object(vec![
+        ("ruleId", string(site.kind())),
+        ("ruleIndex", Json::U64(index as u64)),
+        ("level", string("note")),
+        ("message", text(&message)),
+        ("locations", Json::Array(vec![location(site)])),
+        ("partialFingerprints", object(vec![
+            ("unsafeAstSite/v1", Json::String(format!("{:016x}", site.fingerprint()))),
+        ])),
+        ("properties", object(vec![
+            ("crate", string(site.krate())),
+            ("macroOrigin", Json::String(format!("{:?}", site.macro_origin()))),
+        ])),
+    ])
+}
+
+/// A SARIF log with one run, whose results are the unsafe sites of `crates`. If `src_root` is
+/// given, it is the URI which relative paths are relative to, which SARIF requires to be absolute
+/// (see `is_absolute_uri`).
+pub fn log(crates: &[Crate], src_root: Option<&str>) -> Json {
+    let results = crates.iter()
+        .flat_map(baseline::sites)
+        .map(|site| result(&site))
+        .collect();
+    let driver = object(vec![
+        ("name", string("unsafe-ast")),
+        ("version", string(env!("CARGO_PKG_VERSION"))),
+        ("rules", Json::Array(RULES.iter().map(|&(id, description)| rule(id, description))
+                                          .collect())),
+    ]);
+    let mut run = vec![
+        ("tool", object(vec![("driver", driver)])),
+        ("columnKind", string("unicodeCodePoints")),
+        ("results", Json::Array(results)),
+    ];
+    if let Some(root) = src_root {
+        let root = if root.ends_with('/') { root.to_string() } else { format!("{}/", root) };
+        run.push(("originalUriBaseIds",
+                  object(vec![(SRCROOT, object(vec![("uri", Json::String(root))]))])));
+    }
+    object(vec![
+        ("$schema", string(SCHEMA)),
+        ("version", string(VERSION)),
+        ("runs", Json::Array(vec![object(run)])),
+    ])
+}
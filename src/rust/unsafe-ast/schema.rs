@@ -0,0 +1,399 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// A JSON Schema (draft 4) describing the JSON encoding of the Unsafe AST
+//
+// This has to be kept in step with the structs in `uast.rs`. The tests below
+// check that it is, by validating an encoded UAST against it, and the UASTs
+// that the other tests use as fixtures.
+
+use rustc_serialize::json::Json;
+
+use SCHEMA_VERSION;
+
+use std::collections::BTreeMap;
+
+/// The JSON Schema for a `Crate`, with the definitions of all its parts.
+pub fn schema() -> Json {
+    let mut definitions = BTreeMap::new();
+    for (name, def) in definitions_list() {
+        definitions.insert(name.to_string(), def);
+    }
+    object(vec![
+        ("$schema", string("http://json-schema.org/draft-04/schema#")),
+        ("title", string("Unsafe AST")),
+        ("description", string(&format!("The UAST of one crate, schema version {}",
+                                        SCHEMA_VERSION))),
+        ("definitions", Json::Object(definitions)),
+        ("$ref", string("#/definitions/Crate")),
+    ])
+}
+
+fn definitions_list() -> Vec<(&'static str, Json)> {
+    vec![
+        ("Crate", structure(vec![
+            ("name", ty("string")),
+            ("ty", ty("string")),
+            ("functions", array(reference("FnDecl"))),
+            ("unsafe_impls", array(reference("UnsafeImpl"))),
+            ("unsafe_traits", array(reference("UnsafeTrait"))),
+            ("module_tree", option(reference("Module"))),
+            ("meta", reference("Metadata")),
+        ])),
+        ("Metadata", structure(vec![
+            ("schema_version", object(vec![
+                ("type", string("integer")),
+                ("enum", Json::Array(vec![Json::U64(SCHEMA_VERSION)])),
+            ])),
+            ("package", option(ty("string"))),
+            ("version", option(ty("string"))),
+            ("target_kind", enumeration(&["lib", "bin", "test", "bench", "example"])),
+            ("features", array(ty("string"))),
+            ("compiler_version", ty("string")),
+            ("timestamp", natural()),
+            ("snippet_length", option(natural())),
+            ("encoding", enumeration(&["default", "tagged"])),
+        ])),
+        ("Module", structure(vec![
+            ("path", ty("string")),
+            ("span", ty("string")),
+            ("functions", array(natural())),
+            ("impls", array(reference("Impl"))),
+            ("modules", array(reference("Module"))),
+        ])),
+        ("Impl", structure(vec![
+            ("self_ty", ty("string")),
+            ("trait_path", option(ty("string"))),
+            ("unsaf", ty("boolean")),
+            ("span", ty("string")),
+            ("functions", array(natural())),
+        ])),
+        ("FnDecl", structure(vec![
+            ("name", ty("string")),
+            ("unsaf", ty("boolean")),
+            ("span", ty("string")),
+            ("span_info", option(reference("SpanInfo"))),
+            ("macro_origin", reference("MacroOrigin")),
+            ("sig", reference("Signature")),
+            ("vis", reference("Visibility")),
+            ("exported", ty("boolean")),
+            ("block", reference("Block")),
+        ])),
+        ("Visibility", one_of(vec![
+            enumeration(&["Public", "Crate", "Inherited"]),
+            variant("Restricted", vec![ty("string")]),
+        ])),
+        ("Signature", structure(vec![
+            ("abi", ty("string")),
+            ("inputs", array(ty("string"))),
+            ("output", ty("string")),
+            ("raw_ptr_input", ty("boolean")),
+            ("raw_ptr_output", ty("boolean")),
+            ("type_params", natural()),
+        ])),
+        ("UnsafeImpl", structure(vec![
+            ("trait_path", ty("string")),
+            ("self_ty", ty("string")),
+            ("span", ty("string")),
+            ("macro_origin", reference("MacroOrigin")),
+        ])),
+        ("UnsafeTrait", structure(vec![
+            ("path", ty("string")),
+            ("span", ty("string")),
+            ("macro_origin", reference("MacroOrigin")),
+        ])),
+        ("Block", structure(vec![
+            ("size", natural()),
+            ("unsaf", ty("boolean")),
+            ("contents", array(reference("Indexed"))),
+        ])),
+        ("Indexed", structure(vec![
+            ("index", natural()),
+            ("span", ty("string")),
+            ("span_info", option(reference("SpanInfo"))),
+            ("snippet", ty("string")),
+            ("truncated", ty("boolean")),
+            ("macro_origin", reference("MacroOrigin")),
+            ("item", one_of(vec![reference("UASTNode"), reference("TaggedUASTNode")])),
+        ])),
+        ("SpanInfo", structure(vec![
+            ("file", ty("string")),
+            ("lo_line", natural()),
+            ("lo_col", natural()),
+            ("hi_line", natural()),
+            ("hi_col", natural()),
+            ("lo_byte", natural()),
+            ("hi_byte", natural()),
+        ])),
+        ("UASTNode", one_of(vec![
+            enumeration(&["Deref", "MutStatic", "ExternStatic", "InlineASM",
+                          "UnionFieldRead", "UnionFieldWrite"]),
+            variant("Call", vec![reference("Unsafe"), reference("FFI"), reference("Callee")]),
+            variant("Closure", vec![reference("Block")]),
+            variant("InnerBlock", vec![reference("Block")]),
+        ])),
+        // With `UAST_ENCODING=tagged`, see `encoding.rs`
+        ("TaggedUASTNode", one_of(vec![
+            structure(vec![
+                ("kind", enumeration(&["Deref", "MutStatic", "ExternStatic", "InlineASM",
+                                       "UnionFieldRead", "UnionFieldWrite"])),
+            ]),
+            structure(vec![
+                ("kind", enumeration(&["Call"])),
+                ("unsafe", ty("boolean")),
+                ("ffi", ty("boolean")),
+                ("intrinsic", ty("boolean")),
+                ("abi", ty("string")),
+                ("callee", option(ty("string"))),
+                ("callee_crate", option(ty("string"))),
+            ]),
+            structure(vec![
+                ("kind", enumeration(&["Closure", "InnerBlock"])),
+                ("block", reference("Block")),
+            ]),
+        ])),
+        ("Unsafe", structure(vec![
+            ("unsaf", ty("boolean")),
+        ])),
+        ("FFI", structure(vec![
+            ("is_ffi", ty("boolean")),
+            ("is_intrinsic", ty("boolean")),
+            ("abi", ty("string")),
+        ])),
+        ("Callee", structure(vec![
+            ("path", option(ty("string"))),
+            ("krate", option(ty("string"))),
+        ])),
+        ("MacroOrigin", enumeration(&["NotMacro", "LocalMacro", "ExternalMacro", "DeriveMacro"])),
+    ]
+}
+
+// ================================================ //
+// Helpers for building the parts of a JSON Schema //
+// ================================================ //
+
+fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+fn object(pairs: Vec<(&str, Json)>) -> Json {
+    Json::Object(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn ty(name: &str) -> Json {
+    object(vec![("type", string(name))])
+}
+
+fn natural() -> Json {
+    object(vec![("type", string("integer")), ("minimum", Json::U64(0))])
+}
+
+fn reference(definition: &str) -> Json {
+    object(vec![("$ref", string(&format!("#/definitions/{}", definition)))])
+}
+
+fn array(items: Json) -> Json {
+    object(vec![("type", string("array")), ("items", items)])
+}
+
+fn option(schema: Json) -> Json {
+    one_of(vec![ty("null"), schema])
+}
+
+fn one_of(schemas: Vec<Json>) -> Json {
+    object(vec![("oneOf", Json::Array(schemas))])
+}
+
+fn enumeration(values: &[&str]) -> Json {
+    object(vec![
+        ("type", string("string")),
+        ("enum", Json::Array(values.iter().map(|v| string(v)).collect())),
+    ])
+}
+
+/// A struct is encoded as an object with exactly its fields.
+fn structure(fields: Vec<(&str, Json)>) -> Json {
+    let required = fields.iter().map(|&(name, _)| string(name)).collect();
+    object(vec![
+        ("type", string("object")),
+        ("properties", object(fields)),
+        ("required", Json::Array(required)),
+        ("additionalProperties", Json::Boolean(false)),
+    ])
+}
+
+/// An enum variant with fields is encoded as `{"variant": <name>, "fields": [<field>, ...]}`,
+/// whereas variants without fields are encoded as just their name.
+fn variant(name: &str, fields: Vec<Json>) -> Json {
+    let arity = Json::U64(fields.len() as u64);
+    structure(vec![
+        ("variant", object(vec![("enum", Json::Array(vec![string(name)]))])),
+        ("fields", object(vec![
+            ("type", string("array")),
+            ("items", Json::Array(fields)),
+            ("minItems", arity.clone()),
+            ("maxItems", arity),
+        ])),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::schema;
+    use encoding;
+    use Crate;
+
+    use rustc_serialize::json::{self, Json};
+
+    const SAMPLE: &'static str = include_str!("../../../tests/fixtures/sample.json");
+
+    /// The fixtures of the other tests, with a UAST on each line. `encoding/old.json` isn't
+    /// among them, as it is from before the schema was versioned.
+    const FIXTURES: &'static [(&'static str, &'static str)] = &[
+        ("crates.json", include_str!("../../../tests/fixtures/crates.json")),
+        ("diff/old.json", include_str!("../../../tests/fixtures/diff/old.json")),
+        ("diff/new.json", include_str!("../../../tests/fixtures/diff/new.json")),
+        ("encoding/tagged.json", include_str!("../../../tests/fixtures/encoding/tagged.json")),
+        ("html/crate.json", include_str!("../../../tests/fixtures/html/crate.json")),
+        ("sarif/crate.json", include_str!("../../../tests/fixtures/sarif/crate.json")),
+        ("summary/crate.json", include_str!("../../../tests/fixtures/summary/crate.json")),
+        ("visit/crate.json", include_str!("../../../tests/fixtures/visit/crate.json")),
+    ];
+
+    /// Validates `value` against `schema`, supporting just the keywords used above. `root` is
+    /// the schema that `$ref`s are resolved against, and `path` locates `value` for errors.
+    fn validate(root: &Json, schema: &Json, value: &Json, path: &str) -> Result<(), String> {
+        if let Some(r) = schema.find("$ref").and_then(|r| r.as_string()) {
+            let name = r.trim_left_matches("#/definitions/");
+            let def = try!(root.find_path(&["definitions", name])
+                               .ok_or(format!("{}: unknown reference {}", path, r)));
+            return validate(root, def, value, path);
+        }
+        if let Some(t) = schema.find("type").and_then(|t| t.as_string()) {
+            let matches = match t {
+                "object" => value.is_object(),
+                "array" => value.is_array(),
+                "string" => value.is_string(),
+                "integer" => value.is_i64() || value.is_u64(),
+                "boolean" => value.is_boolean(),
+                "null" => value.is_null(),
+                _ => return Err(format!("{}: unknown type {}", path, t)),
+            };
+            if !matches {
+                return Err(format!("{}: expected {}, found {}", path, t, value));
+            }
+        }
+        if let Some(values) = schema.find("enum").and_then(|e| e.as_array()) {
+            if !values.contains(value) {
+                return Err(format!("{}: {} is not one of {:?}", path, value, values));
+            }
+        }
+        if let Some(min) = schema.find("minimum").and_then(|m| m.as_i64()) {
+            if value.as_i64().map_or(false, |v| v < min) {
+                return Err(format!("{}: {} is less than {}", path, value, min));
+            }
+        }
+        if let Some(schemas) = schema.find("oneOf").and_then(|o| o.as_array()) {
+            let passing = schemas.iter()
+                .filter(|s| validate(root, s, value, path).is_ok())
+                .count();
+            if passing != 1 {
+                return Err(format!("{}: {} matches {} of the alternatives, rather than 1",
+                                   path, value, passing));
+            }
+        }
+        if let Some(obj) = value.as_object() {
+            let properties = schema.find("properties").and_then(|p| p.as_object());
+            if let Some(properties) = properties {
+                for (key, field_schema) in properties {
+                    if let Some(field) = obj.get(key) {
+                        try!(validate(root, field_schema, field, &format!("{}.{}", path, key)));
+                    }
+                }
+            }
+            for key in schema.find("required").and_then(|r| r.as_array()).unwrap_or(&vec![]) {
+                let key = key.as_string().unwrap();
+                if !obj.contains_key(key) {
+                    return Err(format!("{}: missing field {}", path, key));
+                }
+            }
+            if schema.find("additionalProperties") == Some(&Json::Boolean(false)) {
+                for key in obj.keys() {
+                    if !properties.map_or(false, |p| p.contains_key(key)) {
+                        return Err(format!("{}: unexpected field {}", path, key));
+                    }
+                }
+            }
+        }
+        if let Some(items) = value.as_array() {
+            let len = items.len() as u64;
+            if schema.find("minItems").and_then(|m| m.as_u64()).map_or(false, |min| len < min) ||
+               schema.find("maxItems").and_then(|m| m.as_u64()).map_or(false, |max| len > max) {
+                return Err(format!("{}: wrong number of items, {}", path, len));
+            }
+            match schema.find("items") {
+                Some(&Json::Array(ref tuple)) => {
+                    for (i, (item, item_schema)) in items.iter().zip(tuple).enumerate() {
+                        try!(validate(root, item_schema, item, &format!("{}[{}]", path, i)));
+                    }
+                },
+                Some(item_schema) => {
+                    for (i, item) in items.iter().enumerate() {
+                        try!(validate(root, item_schema, item, &format!("{}[{}]", path, i)));
+                    }
+                },
+                None => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Round trips the sample through `Crate`, so that what is validated is what the emitter
+    /// would write.
+    fn encoded_sample() -> Json {
+        let krate: Crate = json::decode(SAMPLE).expect("The sample should decode as a Crate");
+        Json::from_str(&json::encode(&krate).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn encoded_uast_matches_schema() {
+        let schema = schema();
+        assert_eq!(validate(&schema, &schema, &encoded_sample(), "$"), Ok(()));
+    }
+
+    #[test]
+    fn tagged_uast_matches_schema() {
+        let schema = schema();
+        let tagged = encoding::tag(encoded_sample());
+        assert_eq!(validate(&schema, &schema, &tagged, "$"), Ok(()));
+    }
+
+    #[test]
+    fn fixtures_match_schema() {
+        let schema = schema();
+        for &(name, fixture) in FIXTURES {
+            for (n, line) in fixture.lines().enumerate() {
+                let uast = Json::from_str(line).expect("The fixture should be JSON");
+                let path = format!("{}:{}: $", name, n + 1);
+                assert_eq!(validate(&schema, &schema, &uast, &path), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn other_schema_versions_are_rejected() {
+        let schema = schema();
+        let mut uast = encoded_sample();
+        if let Some(&mut Json::Object(ref mut meta)) = uast.as_object_mut()
+                                                           .and_then(|o| o.get_mut("meta")) {
+            meta.insert("schema_version".to_string(), Json::U64(0));
+        }
+        assert!(validate(&schema, &schema, &uast, "$").is_err());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let schema = schema();
+        let mut uast = encoded_sample();
+        uast.as_object_mut().unwrap().insert("unsafe_points".to_string(), Json::Array(vec![]));
+        assert!(validate(&schema, &schema, &uast, "$").is_err());
+    }
+}
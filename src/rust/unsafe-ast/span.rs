@@ -0,0 +1,99 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// Where spans are in the source, from their `span_info` or by parsing their `span` strings
+//
+// `emit-ast` writes each span as rustc prints it, `FILE:LO_LINE:LO_COL: HI_LINE:HI_COL`, and only
+// adds the broken down `span_info` when asked to. Lines and columns count from 1, columns are in
+// characters, and the `hi` position is just past the end of the span, as in `SpanInfo`.
+//
+// Spans in the expansions of macros from other crates name pseudo-files like `<std macros>`, which
+// aren't in the crate's source (see `is_real`). Spans in the source of other crates, such as those
+// of macros from the registry, name them by absolute paths, which aren't either (see
+// `is_within_source`).
+
+use std::cmp::Ordering;
+
+use uast::SpanInfo;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    file: String,
+    lo_line: u64,
+    lo_col: u64,
+    hi_line: u64,
+    hi_col: u64,
+}
+
+impl Span {
+    pub fn new(file: String, lo_line: u64, lo_col: u64, hi_line: u64, hi_col: u64) -> Span {
+        Span { file: file, lo_line: lo_line, lo_col: lo_col, hi_line: hi_line, hi_col: hi_col }
+    }
+
+    /// Parses a span string, such as `src/lib.rs:13:5: 13:7`. The file name may have colons in it.
+    pub fn parse(span: &str) -> Option<Span> {
+        let mut parts = span.rsplitn(2, ": ");
+        let (hi, lo) = match (parts.next(), parts.next()) {
+            (Some(hi), Some(lo)) => (hi, lo),
+            _ => return None,
+        };
+        let mut hi = hi.splitn(2, ':').map(|n| n.parse::<u64>());
+        let mut lo = lo.rsplitn(3, ':');
+        match (lo.next().map(|n| n.parse::<u64>()), lo.next().map(|n| n.parse::<u64>()),
+               lo.next(), hi.next(), hi.next()) {
+            (Some(Ok(lo_col)), Some(Ok(lo_line)), Some(file), Some(Ok(hi_line)),
+             Some(Ok(hi_col))) if !file.is_empty() => {
+                Some(Span::new(file.to_string(), lo_line, lo_col, hi_line, hi_col))
+            },
+            _ => None,
+        }
+    }
+
+    /// The span described by `span_info` if there is one, or by parsing `span` if not.
+    pub fn of(span: &str, span_info: Option<&SpanInfo>) -> Option<Span> {
+        match span_info {
+            Some(info) => Some(Span::from(info)),
+            None => Span::parse(span),
+        }
+    }
+
+    pub fn file(&self) -> &str { &self.file }
+    pub fn lo_line(&self) -> u64 { self.lo_line }
+    pub fn lo_col(&self) -> u64 { self.lo_col }
+    pub fn hi_line(&self) -> u64 { self.hi_line }
+    pub fn hi_col(&self) -> u64 { self.hi_col }
+
+    /// Whether the span is in a file of the crate, rather than in an external macro.
+    pub fn is_real(&self) -> bool {
+        !(self.file.starts_with('<') && self.file.ends_with('>'))
+    }
+
+    /// Whether the file is a relative path which stays inside the directory it is relative to: it
+    /// has no root, drive or `..` components. Separators of either platform are allowed for, since
+    /// the UASTs may come from another one.
+    pub fn is_within_source(&self) -> bool {
+        let mut parts = self.file.split(|c| c == '/' || c == '\\');
+        let first = parts.next().unwrap_or("");
+        let outside = first.is_empty() || first.ends_with(':') || first == "..";
+        !outside && parts.all(|part| part != "..")
+    }
+
+    /// Whether the character at `line` and `col` is in the span.
+    pub fn contains(&self, line: u64, col: u64) -> bool {
+        (self.lo_line, self.lo_col) <= (line, col) && (line, col) < (self.hi_line, self.hi_col)
+    }
+
+    /// Orders spans by where they start, and then with the outermost first.
+    pub fn cmp_position(&self, other: &Span) -> Ordering {
+        match (&self.file, self.lo_line, self.lo_col)
+                  .cmp(&(&other.file, other.lo_line, other.lo_col)) {
+            Ordering::Equal => (other.hi_line, other.hi_col).cmp(&(self.hi_line, self.hi_col)),
+            ordering => ordering,
+        }
+    }
+}
+
+impl<'a> From<&'a SpanInfo> for Span {
+    fn from(info: &'a SpanInfo) -> Span {
+        Span::new(info.file().to_string(), info.lo_line(), info.lo_col(), info.hi_line(),
+                  info.hi_col())
+    }
+}
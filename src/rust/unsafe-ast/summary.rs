@@ -0,0 +1,207 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// A summary of the unsafe code in a crate, for a quick look without writing jq
+//
+// The counts are of the same things as the analyses in `analysis.rs`: unsafe blocks are those of
+// `unsafe_blocks`, uses those of `all_unsafe_uses`, and the means are of `block_requirement` and
+// `block_relative_size` over `unsafe_blocks_with_functions`. A function's unsafe density is the
+// number of unsafe uses in it per statement (`block_net_size_2` of its body).
+
+use rustc_serialize::json::{Json,Object};
+
+use std::cmp::{self,Ordering};
+use std::collections::BTreeMap;
+
+use analysis;
+use uast::{Crate,UASTNode};
+
+/// The number of functions `Summary::new` lists by unsafe density, unless told otherwise.
+pub const DEFAULT_TOP: usize = 10;
+
+/// How dense the unsafe uses in a function are.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Density {
+    function: String,
+    unsafe_uses: u64,
+    size: u64,
+}
+
+impl Density {
+    pub fn function(&self) -> &str { &self.function }
+    pub fn unsafe_uses(&self) -> u64 { self.unsafe_uses }
+    /// The number of statements in the function, as `block_net_size_2` counts them.
+    pub fn size(&self) -> u64 { self.size }
+    /// Unsafe uses per statement, counting a function of no statements as having one.
+    pub fn density(&self) -> f64 { self.unsafe_uses as f64 / cmp::max(self.size, 1) as f64 }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Summary {
+    name: String,
+    version: Option<String>,
+    target_kind: String,
+    functions: u64,
+    unsafe_functions: u64,
+    unsafe_blocks: u64,
+    uses: BTreeMap<String, u64>,
+    ffi_calls: u64,
+    intrinsic_calls: u64,
+    rust_calls: u64,
+    macro_origins: BTreeMap<String, u64>,
+    mean_block_requirement: Option<f64>,
+    mean_relative_size: Option<f64>,
+    densest: Vec<Density>,
+}
+
+fn mean(xs: &[f64]) -> Option<f64> {
+    if xs.is_empty() { None } else { Some(analysis::mean(xs)) }
+}
+
+impl Summary {
+    /// Summarizes `krate`, listing the `top` functions with the densest unsafe uses.
+    pub fn new(krate: &Crate, top: usize) -> Summary {
+        let mut uses = BTreeMap::new();
+        let mut macro_origins = BTreeMap::new();
+        let (mut ffi_calls, mut intrinsic_calls, mut rust_calls) = (0, 0, 0);
+        for node in analysis::all_unsafe_uses(krate) {
+            *uses.entry(node.item().kind().to_string()).or_insert(0) += 1;
+            *macro_origins.entry(format!("{:?}", node.macro_origin())).or_insert(0) += 1;
+            if let UASTNode::Call(_, ref ffi, _) = *node.item() {
+                if ffi.is_ffi() {
+                    ffi_calls += 1;
+                } else if ffi.is_intrinsic() {
+                    intrinsic_calls += 1;
+                } else {
+                    rust_calls += 1;
+                }
+            }
+        }
+
+        let mut densest: Vec<Density> = krate.functions().iter()
+            .map(|function| Density {
+                function: function.name().to_string(),
+                unsafe_uses: function.block().all_uses().iter()
+                    .filter(|node| node.item().is_unsafe_use())
+                    .count() as u64,
+                size: cmp::max(analysis::block_net_size_2(function.block()), 0) as u64,
+            })
+            .filter(|density| density.unsafe_uses > 0)
+            .collect();
+        // Densest first, then those with the most uses, then by name
+        densest.sort_by(|a, b| {
+            match b.density().partial_cmp(&a.density()).unwrap_or(Ordering::Equal) {
+                Ordering::Equal => (b.unsafe_uses, &a.function).cmp(&(a.unsafe_uses, &b.function)),
+                ordering => ordering,
+            }
+        });
+        densest.truncate(top);
+
+        Summary {
+            name: krate.name().to_string(),
+            version: krate.meta().version().map(|v| v.to_string()),
+            target_kind: krate.meta().target_kind().to_string(),
+            functions: krate.functions().len() as u64,
+            unsafe_functions: krate.functions().iter().filter(|f| f.unsaf()).count() as u64,
+            unsafe_blocks: analysis::unsafe_blocks(krate).len() as u64,
+            uses: uses,
+            ffi_calls: ffi_calls,
+            intrinsic_calls: intrinsic_calls,
+            rust_calls: rust_calls,
+            macro_origins: macro_origins,
+            mean_block_requirement: mean(&analysis::unsafe_blocks_requirement(krate)),
+            mean_relative_size: mean(&analysis::unsafe_blocks_rel_sizes(krate)),
+            densest: densest,
+        }
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn version(&self) -> Option<&str> { self.version.as_ref().map(|v| &v[..]) }
+    pub fn functions(&self) -> u64 { self.functions }
+    pub fn unsafe_functions(&self) -> u64 { self.unsafe_functions }
+    pub fn unsafe_blocks(&self) -> u64 { self.unsafe_blocks }
+    /// The number of unsafe uses of each kind (see `UASTNode::kind`).
+    pub fn uses(&self) -> &BTreeMap<String, u64> { &self.uses }
+    pub fn unsafe_uses(&self) -> u64 { self.uses.values().sum() }
+    /// Unsafe calls of foreign functions.
+    pub fn ffi_calls(&self) -> u64 { self.ffi_calls }
+    /// Unsafe calls of compiler intrinsics.
+    pub fn intrinsic_calls(&self) -> u64 { self.intrinsic_calls }
+    /// Unsafe calls of Rust functions.
+    pub fn rust_calls(&self) -> u64 { self.rust_calls }
+    /// The number of unsafe uses from each `MacroOrigin`.
+    pub fn macro_origins(&self) -> &BTreeMap<String, u64> { &self.macro_origins }
+    /// The mean `block_requirement` of the unsafe blocks, if there are any.
+    pub fn mean_block_requirement(&self) -> Option<f64> { self.mean_block_requirement }
+    /// The mean `block_relative_size` of the unsafe blocks, if there are any.
+    pub fn mean_relative_size(&self) -> Option<f64> { self.mean_relative_size }
+    /// The functions with the densest unsafe uses, densest first.
+    pub fn densest(&self) -> &[Density] { &self.densest }
+
+    /// A report for people.
+    pub fn to_text(&self) -> String {
+        let mut out = format!("{} {} ({})\n", self.name, self.version().unwrap_or("(no version)"),
+                              self.target_kind);
+        out.push_str(&format!("  functions: {}, {} unsafe\n", self.functions,
+                              self.unsafe_functions));
+        out.push_str(&format!("  unsafe blocks: {}\n", self.unsafe_blocks));
+        out.push_str(&format!("  unsafe uses: {}\n", self.unsafe_uses()));
+        for (kind, count) in &self.uses {
+            out.push_str(&format!("    {:<16}{}\n", kind, count));
+        }
+        out.push_str(&format!("  unsafe calls: {} FFI, {} intrinsic, {} Rust\n", self.ffi_calls,
+                              self.intrinsic_calls, self.rust_calls));
+        out.push_str("  unsafe uses by macro origin:\n");
+        for (origin, count) in &self.macro_origins {
+            out.push_str(&format!("    {:<16}{}\n", origin, count));
+        }
+        let show = |x: Option<f64>| x.map_or("-".to_string(), |x| format!("{:.3}", x));
+        out.push_str(&format!("  mean block requirement: {}\n",
+                              show(self.mean_block_requirement)));
+        out.push_str(&format!("  mean relative block size: {}\n", show(self.mean_relative_size)));
+        if !self.densest.is_empty() {
+            out.push_str("  densest functions (unsafe uses per statement):\n");
+            for density in &self.densest {
+                out.push_str(&format!("    {:.3}  {} ({} in {})\n", density.density(),
+                                      density.function, density.unsafe_uses, density.size));
+            }
+        }
+        out
+    }
+
+    /// A report for tools, with the fields of the text report under the names of the accessors.
+    pub fn to_json(&self) -> Json {
+        let counts = |map: &BTreeMap<String, u64>| {
+            Json::Object(map.iter().map(|(k, v)| (k.clone(), Json::U64(*v))).collect())
+        };
+        let mut calls = Object::new();
+        calls.insert("ffi".to_string(), Json::U64(self.ffi_calls));
+        calls.insert("intrinsic".to_string(), Json::U64(self.intrinsic_calls));
+        calls.insert("rust".to_string(), Json::U64(self.rust_calls));
+        let densest = self.densest.iter().map(|density| {
+            let mut obj = Object::new();
+            obj.insert("function".to_string(), Json::String(density.function.clone()));
+            obj.insert("unsafe_uses".to_string(), Json::U64(density.unsafe_uses));
+            obj.insert("size".to_string(), Json::U64(density.size));
+            obj.insert("density".to_string(), Json::F64(density.density()));
+            Json::Object(obj)
+        }).collect();
+
+        let mut obj = Object::new();
+        obj.insert("name".to_string(), Json::String(self.name.clone()));
+        obj.insert("version".to_string(),
+                   self.version.as_ref().map_or(Json::Null, |v| Json::String(v.clone())));
+        obj.insert("target_kind".to_string(), Json::String(self.target_kind.clone()));
+        obj.insert("functions".to_string(), Json::U64(self.functions));
+        obj.insert("unsafe_functions".to_string(), Json::U64(self.unsafe_functions));
+        obj.insert("unsafe_blocks".to_string(), Json::U64(self.unsafe_blocks));
+        obj.insert("unsafe_uses".to_string(), Json::U64(self.unsafe_uses()));
+        obj.insert("uses".to_string(), counts(&self.uses));
+        obj.insert("unsafe_calls".to_string(), Json::Object(calls));
+        obj.insert("macro_origins".to_string(), counts(&self.macro_origins));
+        obj.insert("mean_block_requirement".to_string(),
+                   self.mean_block_requirement.map_or(Json::Null, Json::F64));
+        obj.insert("mean_relative_size".to_string(),
+                   self.mean_relative_size.map_or(Json::Null, Json::F64));
+        obj.insert("densest".to_string(), Json::Array(densest));
+        Json::Object(obj)
+    }
+}
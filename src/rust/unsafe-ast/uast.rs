@@ -0,0 +1,533 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// The Unsafe AST itself, which summarizes the bits of a crate related to `unsafe`
+
+/// The version of the UAST format. Bump this whenever the encoded UAST changes shape.
+pub const SCHEMA_VERSION: u64 = 1;
+
+// ===================================== //
+// The Unsafe AST itself                 //
+// ===================================== //
+
+
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Crate {
+    name: String,
+    ty: String,
+    functions: Vec<FnDecl>,
+    unsafe_impls: Vec<UnsafeImpl>,
+    unsafe_traits: Vec<UnsafeTrait>,
+    module_tree: Option<Module>,
+    meta: Metadata,
+}
+
+/// How, when and from what a UAST was produced, so that datasets from different runs can be joined
+/// and records from incompatible emitters rejected. The package fields are only known when cargo
+/// is running the build.
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Metadata {
+    schema_version: u64,
+    /// `<name>:<version>`. Unlike a cargo package id, this doesn't say where the package came from,
+    /// so a crate from crates.io and a git or path crate of the same name and version share it.
+    package: Option<String>,
+    version: Option<String>,
+    target_kind: String,
+    features: Vec<String>,
+    compiler_version: String,
+    /// Seconds since the Unix epoch
+    timestamp: u64,
+    /// The most characters `emit-ast` kept of each snippet (`UAST_SNIPPET_LENGTH`), or `None` if it
+    /// kept them whole
+    snippet_length: Option<u64>,
+    /// How the UAST nodes are encoded: "default", or "tagged" (see `encoding.rs`)
+    encoding: String,
+}
+
+/// A module, with the functions and impls directly inside it. Functions are referred to by their
+/// position in `Crate.functions`. The crate root has the empty path.
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Module {
+    path: String,
+    span: String,
+    functions: Vec<u64>,
+    impls: Vec<Impl>,
+    modules: Vec<Module>,
+}
+
+/// An impl block, inherent or of a trait, with the positions of its methods in `Crate.functions`.
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Impl {
+    self_ty: String,
+    trait_path: Option<String>,
+    unsaf: bool,
+    span: String,
+    functions: Vec<u64>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct FnDecl {
+    name: String,
+    unsaf: bool,
+    span: String,
+    span_info: Option<SpanInfo>,
+    macro_origin: MacroOrigin,
+    sig: Signature,
+    vis: Visibility,
+    exported: bool,
+    block: Box<Block>,
+}
+
+/// The visibility written on an item. Trait methods, and the methods of trait impls, have
+/// `Inherited` visibility, and are as visible as their trait.
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub enum Visibility {
+    Public,
+    Crate,
+    Restricted(String),
+    Inherited,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Signature {
+    abi: String,
+    inputs: Vec<String>,
+    output: String,
+    raw_ptr_input: bool,
+    raw_ptr_output: bool,
+    type_params: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct UnsafeImpl {
+    trait_path: String,
+    self_ty: String,
+    span: String,
+    macro_origin: MacroOrigin,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct UnsafeTrait {
+    path: String,
+    span: String,
+    macro_origin: MacroOrigin,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Block {
+    size: u64,
+    unsaf: bool,
+    contents: Vec<Indexed<UASTNode>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Indexed<T> {
+    index: u64,
+    span: String,
+    span_info: Option<SpanInfo>,
+    snippet: String,
+    truncated: bool,
+    macro_origin: MacroOrigin,
+    item: T,
+}
+
+/// The location of a span. Lines and columns count from 1, byte offsets from the start of the file
+/// at 0, and the `hi` position is just past the end of the span.
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct SpanInfo {
+    file: String,
+    lo_line: u64,
+    lo_col: u64,
+    hi_line: u64,
+    hi_col: u64,
+    lo_byte: u64,
+    hi_byte: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub enum UASTNode {
+    Deref,
+    MutStatic,
+    ExternStatic,
+    InlineASM,
+    UnionFieldRead,
+    UnionFieldWrite,
+    Call(Unsafe,FFI,Callee),
+    Closure(Box<Block>),
+    InnerBlock(Box<Block>),
+}
+
+/// How a call crosses out of Rust: `is_ffi` for foreign ABIs (`C`, `system`, `stdcall`, ...) and
+/// `is_intrinsic` for compiler intrinsics (`rust-intrinsic`, `platform-intrinsic`). `abi` is ""
+/// when it isn't known, as for records from before ABIs were recorded.
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct FFI {
+    is_ffi: bool,
+    is_intrinsic: bool,
+    abi: String,
+}
+
+/// What a call invokes. Both fields are `None` for calls through fn pointers and closures.
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Callee {
+    path: Option<String>,
+    krate: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub enum MacroOrigin {
+    NotMacro, LocalMacro, ExternalMacro, DeriveMacro
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Unsafe {
+    unsaf: bool,
+}
+
+// ===================================== //
+// Implementations for parts of the UAST //
+// ===================================== //
+
+impl Crate {
+    pub fn new(name: String,
+               ty: String,
+               functions: Vec<FnDecl>,
+               unsafe_impls: Vec<UnsafeImpl>,
+               unsafe_traits: Vec<UnsafeTrait>,
+               module_tree: Option<Module>,
+               meta: Metadata) -> Self {
+        Crate { name: name,
+                ty: ty,
+                functions: functions,
+                unsafe_impls: unsafe_impls,
+                unsafe_traits: unsafe_traits,
+                module_tree: module_tree,
+                meta: meta,
+        }
+    }
+    pub fn name(&self) -> &str { &self.name }
+    /// The crate type rustc built, e.g. "CrateTypeRlib"
+    pub fn ty(&self) -> &str { &self.ty }
+    pub fn functions(&self) -> &[FnDecl] { &self.functions }
+    pub fn unsafe_impls(&self) -> &[UnsafeImpl] { &self.unsafe_impls }
+    pub fn unsafe_traits(&self) -> &[UnsafeTrait] { &self.unsafe_traits }
+    /// Only present if the emitter was run with `UAST_MODULE_TREE` set
+    pub fn module_tree(&self) -> Option<&Module> { self.module_tree.as_ref() }
+    pub fn meta(&self) -> &Metadata { &self.meta }
+}
+
+impl Metadata {
+    pub fn new(package: Option<String>,
+               version: Option<String>,
+               target_kind: String,
+               features: Vec<String>,
+               compiler_version: String,
+               timestamp: u64,
+               snippet_length: Option<u64>) -> Self {
+        Metadata { schema_version: SCHEMA_VERSION,
+                   package: package,
+                   version: version,
+                   target_kind: target_kind,
+                   features: features,
+                   compiler_version: compiler_version,
+                   timestamp: timestamp,
+                   snippet_length: snippet_length,
+                   encoding: "default".to_string(),
+        }
+    }
+    /// 0 for records written before the UAST was versioned
+    pub fn schema_version(&self) -> u64 { self.schema_version }
+    pub fn package(&self) -> Option<&str> { self.package.as_ref().map(|s| &s[..]) }
+    pub fn version(&self) -> Option<&str> { self.version.as_ref().map(|s| &s[..]) }
+    pub fn target_kind(&self) -> &str { &self.target_kind }
+    pub fn features(&self) -> &[String] { &self.features }
+    pub fn compiler_version(&self) -> &str { &self.compiler_version }
+    pub fn timestamp(&self) -> u64 { self.timestamp }
+    pub fn snippet_length(&self) -> Option<u64> { self.snippet_length }
+    pub fn encoding(&self) -> &str { &self.encoding }
+}
+
+impl Module {
+    pub fn new(path: String,
+               span: String,
+               functions: Vec<u64>,
+               impls: Vec<Impl>,
+               modules: Vec<Module>) -> Self {
+        Module { path: path, span: span, functions: functions, impls: impls, modules: modules }
+    }
+    pub fn path(&self) -> &str { &self.path }
+    pub fn span(&self) -> &str { &self.span }
+    pub fn functions(&self) -> &[u64] { &self.functions }
+    pub fn impls(&self) -> &[Impl] { &self.impls }
+    pub fn modules(&self) -> &[Module] { &self.modules }
+}
+
+impl Impl {
+    pub fn new(self_ty: String,
+               trait_path: Option<String>,
+               unsaf: bool,
+               span: String,
+               functions: Vec<u64>) -> Self {
+        Impl { self_ty: self_ty,
+               trait_path: trait_path,
+               unsaf: unsaf,
+               span: span,
+               functions: functions,
+        }
+    }
+    pub fn self_ty(&self) -> &str { &self.self_ty }
+    /// `None` for inherent impls
+    pub fn trait_path(&self) -> Option<&str> { self.trait_path.as_ref().map(|s| &s[..]) }
+    pub fn unsaf(&self) -> bool { self.unsaf }
+    pub fn span(&self) -> &str { &self.span }
+    pub fn functions(&self) -> &[u64] { &self.functions }
+}
+
+impl FnDecl {
+    pub fn new(block: Box<Block>,
+               unsaf: bool,
+               name: String,
+               span: String,
+               span_info: Option<SpanInfo>,
+               macro_origin: MacroOrigin,
+               sig: Signature,
+               vis: Visibility,
+               exported: bool) -> Self {
+        FnDecl { unsaf: unsaf,
+                 block: block,
+                 name: name,
+                 span: span,
+                 span_info: span_info,
+                 macro_origin: macro_origin,
+                 sig: sig,
+                 vis: vis,
+                 exported: exported,
+        }
+    }
+    pub fn name(&self) -> &str { &self.name }
+    pub fn unsaf(&self) -> bool { self.unsaf }
+    pub fn span(&self) -> &str { &self.span }
+    pub fn span_info(&self) -> Option<&SpanInfo> { self.span_info.as_ref() }
+    pub fn macro_origin(&self) -> MacroOrigin { self.macro_origin }
+    pub fn sig(&self) -> &Signature { &self.sig }
+    pub fn vis(&self) -> &Visibility { &self.vis }
+    /// Whether the fn is reachable from outside the crate
+    pub fn exported(&self) -> bool { self.exported }
+    pub fn block(&self) -> &Block { &self.block }
+}
+
+impl Signature {
+    pub fn new(abi: String,
+               inputs: Vec<String>,
+               output: String,
+               raw_ptr_input: bool,
+               raw_ptr_output: bool,
+               type_params: u64) -> Self {
+        Signature { abi: abi,
+                    inputs: inputs,
+                    output: output,
+                    raw_ptr_input: raw_ptr_input,
+                    raw_ptr_output: raw_ptr_output,
+                    type_params: type_params,
+        }
+    }
+    pub fn abi(&self) -> &str { &self.abi }
+    pub fn inputs(&self) -> &[String] { &self.inputs }
+    pub fn output(&self) -> &str { &self.output }
+    pub fn raw_ptr_input(&self) -> bool { self.raw_ptr_input }
+    pub fn raw_ptr_output(&self) -> bool { self.raw_ptr_output }
+    pub fn type_params(&self) -> u64 { self.type_params }
+}
+
+impl UnsafeImpl {
+    pub fn new(trait_path: String,
+               self_ty: String,
+               span: String,
+               macro_origin: MacroOrigin) -> Self {
+        UnsafeImpl { trait_path: trait_path,
+                     self_ty: self_ty,
+                     span: span,
+                     macro_origin: macro_origin,
+        }
+    }
+    pub fn trait_path(&self) -> &str { &self.trait_path }
+    /// ".." for `unsafe impl Trait for .. {}`
+    pub fn self_ty(&self) -> &str { &self.self_ty }
+    pub fn span(&self) -> &str { &self.span }
+    pub fn macro_origin(&self) -> MacroOrigin { self.macro_origin }
+}
+
+impl UnsafeTrait {
+    pub fn new(path: String, span: String, macro_origin: MacroOrigin) -> Self {
+        UnsafeTrait { path: path, span: span, macro_origin: macro_origin }
+    }
+    pub fn path(&self) -> &str { &self.path }
+    pub fn span(&self) -> &str { &self.span }
+    pub fn macro_origin(&self) -> MacroOrigin { self.macro_origin }
+}
+
+impl Block {
+    pub fn new(unsaf: bool, size: u64, contents: Vec<Indexed<UASTNode>>) -> Block {
+        Block { unsaf: unsaf, size: size, contents: contents }
+    }
+    /// The number of statements in the block, counting a trailing expression
+    pub fn size(&self) -> u64 { self.size }
+    pub fn unsaf(&self) -> bool { self.unsaf }
+    pub fn contents(&self) -> &[Indexed<UASTNode>] { &self.contents }
+
+    /// The blocks directly inside this one: its inner blocks and closures.
+    pub fn children(&self) -> Vec<&Block> {
+        self.contents.iter().filter_map(|node| node.item.block()).collect()
+    }
+
+    /// All of the uses in this block, including those in nested blocks and closures. Like
+    /// `get_all_uses` in `unsafe.jq`, a block's own uses come before those of its nested blocks.
+    pub fn all_uses(&self) -> Vec<&Indexed<UASTNode>> {
+        let mut uses = vec![];
+        self.collect_uses(true, &mut uses);
+        uses
+    }
+
+    /// The uses which are this block's own responsibility: those in it and in the safe blocks and
+    /// closures nested in it, but not those in nested unsafe blocks.
+    pub fn own_uses(&self) -> Vec<&Indexed<UASTNode>> {
+        let mut uses = vec![];
+        self.collect_uses(false, &mut uses);
+        uses
+    }
+
+    fn collect_uses<'a>(&'a self, into_unsafe: bool, uses: &mut Vec<&'a Indexed<UASTNode>>) {
+        uses.extend(self.contents.iter().filter(|node| node.item.is_use()));
+        for block in self.children() {
+            if into_unsafe || !block.unsaf {
+                block.collect_uses(into_unsafe, uses);
+            }
+        }
+    }
+}
+
+impl<T> Indexed<T> {
+    /// Create a new indexed item with
+    ///     `index` - its statement number in the enclosing block
+    ///     `span` - span of the item
+    ///     `span_info` - the span, broken down, if the emitter was asked for it
+    ///     `snippet` - the (possibly truncated) code of the item
+    ///     `truncated` - whether `snippet` was cut short
+    ///     `macro_origin` - whether this item originated in a macro
+    pub fn new(index: u64,
+               item: T,
+               span: String,
+               span_info: Option<SpanInfo>,
+               snippet: String,
+               truncated: bool,
+               macro_origin: MacroOrigin) -> Indexed<T> {
+        Indexed {
+            index: index,
+            span: span,
+            span_info: span_info,
+            snippet: snippet,
+            truncated: truncated,
+            macro_origin: macro_origin,
+            item: item,
+        }
+    }
+    pub fn index(&self) -> u64 { self.index }
+    pub fn span(&self) -> &str { &self.span }
+    pub fn span_info(&self) -> Option<&SpanInfo> { self.span_info.as_ref() }
+    pub fn snippet(&self) -> &str { &self.snippet }
+    pub fn truncated(&self) -> bool { self.truncated }
+    pub fn macro_origin(&self) -> MacroOrigin { self.macro_origin }
+    pub fn item(&self) -> &T { &self.item }
+    pub fn into_item(self) -> T { self.item }
+}
+
+impl SpanInfo {
+    pub fn new(file: String,
+               lo_line: u64,
+               lo_col: u64,
+               hi_line: u64,
+               hi_col: u64,
+               lo_byte: u64,
+               hi_byte: u64) -> SpanInfo {
+        SpanInfo { file: file,
+                   lo_line: lo_line,
+                   lo_col: lo_col,
+                   hi_line: hi_line,
+                   hi_col: hi_col,
+                   lo_byte: lo_byte,
+                   hi_byte: hi_byte,
+        }
+    }
+    pub fn file(&self) -> &str { &self.file }
+    pub fn lo_line(&self) -> u64 { self.lo_line }
+    pub fn lo_col(&self) -> u64 { self.lo_col }
+    pub fn hi_line(&self) -> u64 { self.hi_line }
+    pub fn hi_col(&self) -> u64 { self.hi_col }
+    pub fn lo_byte(&self) -> u64 { self.lo_byte }
+    pub fn hi_byte(&self) -> u64 { self.hi_byte }
+}
+
+impl UASTNode {
+    /// The name of the node's variant, as used in the encodings.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            UASTNode::Deref => "Deref",
+            UASTNode::MutStatic => "MutStatic",
+            UASTNode::ExternStatic => "ExternStatic",
+            UASTNode::InlineASM => "InlineASM",
+            UASTNode::UnionFieldRead => "UnionFieldRead",
+            UASTNode::UnionFieldWrite => "UnionFieldWrite",
+            UASTNode::Call(..) => "Call",
+            UASTNode::Closure(_) => "Closure",
+            UASTNode::InnerBlock(_) => "InnerBlock",
+        }
+    }
+
+    /// The block of a `Closure` or `InnerBlock`.
+    pub fn block(&self) -> Option<&Block> {
+        match *self {
+            UASTNode::Closure(ref block) |
+            UASTNode::InnerBlock(ref block) => Some(block),
+            _ => None,
+        }
+    }
+
+    /// Whether the node is a use (anything but a block or closure).
+    pub fn is_use(&self) -> bool {
+        self.block().is_none()
+    }
+
+    /// Whether the node is an operation which needs an `unsafe` context.
+    pub fn is_unsafe_use(&self) -> bool {
+        match *self {
+            UASTNode::Call(ref unsaf, _, _) => unsaf.unsaf,
+            UASTNode::Closure(_) | UASTNode::InnerBlock(_) => false,
+            _ => true,
+        }
+    }
+}
+
+impl FFI {
+    pub fn new(is_ffi: bool, is_intrinsic: bool, abi: String) -> FFI {
+        FFI { is_ffi: is_ffi, is_intrinsic: is_intrinsic, abi: abi }
+    }
+    pub fn is_ffi(&self) -> bool { self.is_ffi }
+    pub fn is_intrinsic(&self) -> bool { self.is_intrinsic }
+    pub fn abi(&self) -> &str { &self.abi }
+}
+
+impl Callee {
+    pub fn new(path: String, krate: String) -> Callee {
+        Callee { path: Some(path), krate: Some(krate) }
+    }
+    pub fn indirect() -> Callee {
+        Callee { path: None, krate: None }
+    }
+    pub fn path(&self) -> Option<&str> { self.path.as_ref().map(|s| &s[..]) }
+    pub fn krate(&self) -> Option<&str> { self.krate.as_ref().map(|s| &s[..]) }
+}
+
+impl Unsafe {
+    pub fn new(unsaf: bool) -> Unsafe {
+        Unsafe { unsaf: unsaf }
+    }
+    pub fn unsaf(&self) -> bool { self.unsaf }
+}
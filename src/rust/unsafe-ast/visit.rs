@@ -0,0 +1,182 @@
+// Alex Ozdemir <aozdemir@hmc.edu>
+// A visitor over decoded UASTs
+//
+// Like rustc's `intravisit`, each `visit_*` method of `UastVisitor` defaults to walking the
+// children of what it visits, using the `walk_*` function of the same name, so a visitor only
+// overrides the methods for what it is interested in. Overriding methods should call the `walk_*`
+// function themselves to keep descending.
+//
+// Every method below the crate gets a `Context`, which says where in the crate the visited part
+// is: its function, and the blocks around it.
+
+use uast::{Block,Callee,Crate,FFI,FnDecl,Indexed,UASTNode,Unsafe};
+
+/// Where a part of the UAST is within its crate.
+#[derive(Clone, Debug)]
+pub struct Context<'a> {
+    function: Option<&'a FnDecl>,
+    blocks: Vec<&'a Block>,
+}
+
+impl<'a> Context<'a> {
+    /// The context of something at the top level of a crate.
+    pub fn new() -> Context<'a> {
+        Context { function: None, blocks: vec![] }
+    }
+
+    /// The context of the parts of `block`, which is in this context.
+    pub fn enter_block(&self, block: &'a Block) -> Context<'a> {
+        let mut blocks = self.blocks.clone();
+        blocks.push(block);
+        Context { function: self.function, blocks: blocks }
+    }
+
+    /// The context of the parts of `function`.
+    pub fn enter_fn(&self, function: &'a FnDecl) -> Context<'a> {
+        Context { function: Some(function), blocks: vec![] }
+    }
+
+    /// The enclosing function.
+    pub fn function(&self) -> Option<&'a FnDecl> {
+        self.function
+    }
+
+    /// The enclosing blocks, outermost (the function's body) first. Closure bodies count as
+    /// blocks.
+    pub fn blocks(&self) -> &[&'a Block] {
+        &self.blocks
+    }
+
+    /// The innermost enclosing block.
+    pub fn block(&self) -> Option<&'a Block> {
+        self.blocks.last().cloned()
+    }
+
+    /// The innermost enclosing `unsafe` block.
+    pub fn unsafe_block(&self) -> Option<&'a Block> {
+        self.blocks.iter().rev().find(|block| block.unsaf()).cloned()
+    }
+
+    /// The number of enclosing blocks. The top level of a function's body has depth 1.
+    pub fn depth(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Whether unsafe operations are allowed here: inside an `unsafe` block or `unsafe fn`.
+    pub fn in_unsafe(&self) -> bool {
+        self.unsafe_block().is_some() || self.function.map_or(false, |f| f.unsaf())
+    }
+}
+
+impl<'a> Default for Context<'a> {
+    fn default() -> Context<'a> {
+        Context::new()
+    }
+}
+
+/// Visits the parts of a UAST. `'a` is the lifetime of the UAST, so visitors may keep references
+/// into it.
+pub trait UastVisitor<'a>: Sized {
+    fn visit_crate(&mut self, krate: &'a Crate) {
+        walk_crate(self, krate)
+    }
+    fn visit_fn(&mut self, function: &'a FnDecl, cx: &Context<'a>) {
+        walk_fn(self, function, cx)
+    }
+    /// `cx` is the context the block is in, not including the block itself.
+    fn visit_block(&mut self, block: &'a Block, cx: &Context<'a>) {
+        walk_block(self, block, cx)
+    }
+    fn visit_node(&mut self, node: &'a Indexed<UASTNode>, cx: &Context<'a>) {
+        walk_node(self, node, cx)
+    }
+
+    // One method for each kind of node. Each gets the whole `Indexed` node, for its location.
+
+    fn visit_deref(&mut self, _node: &'a Indexed<UASTNode>, _cx: &Context<'a>) {}
+    fn visit_mut_static(&mut self, _node: &'a Indexed<UASTNode>, _cx: &Context<'a>) {}
+    fn visit_extern_static(&mut self, _node: &'a Indexed<UASTNode>, _cx: &Context<'a>) {}
+    fn visit_inline_asm(&mut self, _node: &'a Indexed<UASTNode>, _cx: &Context<'a>) {}
+    fn visit_union_field_read(&mut self, _node: &'a Indexed<UASTNode>, _cx: &Context<'a>) {}
+    fn visit_union_field_write(&mut self, _node: &'a Indexed<UASTNode>, _cx: &Context<'a>) {}
+    fn visit_call(&mut self,
+                  _node: &'a Indexed<UASTNode>,
+                  _unsaf: &'a Unsafe,
+                  _ffi: &'a FFI,
+                  _callee: &'a Callee,
+                  _cx: &Context<'a>) {}
+    fn visit_closure(&mut self,
+                     _node: &'a Indexed<UASTNode>,
+                     block: &'a Block,
+                     cx: &Context<'a>) {
+        self.visit_block(block, cx)
+    }
+    fn visit_inner_block(&mut self,
+                         _node: &'a Indexed<UASTNode>,
+                         block: &'a Block,
+                         cx: &Context<'a>) {
+        self.visit_block(block, cx)
+    }
+}
+
+pub fn walk_crate<'a, V: UastVisitor<'a>>(visitor: &mut V, krate: &'a Crate) {
+    let cx = Context::new();
+    for function in krate.functions() {
+        visitor.visit_fn(function, &cx);
+    }
+}
+
+pub fn walk_fn<'a, V: UastVisitor<'a>>(visitor: &mut V, function: &'a FnDecl, cx: &Context<'a>) {
+    visitor.visit_block(function.block(), &cx.enter_fn(function));
+}
+
+pub fn walk_block<'a, V: UastVisitor<'a>>(visitor: &mut V, block: &'a Block, cx: &Context<'a>) {
+    let cx = cx.enter_block(block);
+    for node in block.contents() {
+        visitor.visit_node(node, &cx);
+    }
+}
+
+pub fn walk_node<'a, V: UastVisitor<'a>>(visitor: &mut V,
+                                         node: &'a Indexed<UASTNode>,
+                                         cx: &Context<'a>) {
+    match *node.item() {
+        UASTNode::Deref => visitor.visit_deref(node, cx),
+        UASTNode::MutStatic => visitor.visit_mut_static(node, cx),
+        UASTNode::ExternStatic => visitor.visit_extern_static(node, cx),
+        UASTNode::InlineASM => visitor.visit_inline_asm(node, cx),
+        UASTNode::UnionFieldRead => visitor.visit_union_field_read(node, cx),
+        UASTNode::UnionFieldWrite => visitor.visit_union_field_write(node, cx),
+        UASTNode::Call(ref unsaf, ref ffi, ref callee) =>
+            visitor.visit_call(node, unsaf, ffi, callee, cx),
+        UASTNode::Closure(ref block) => visitor.visit_closure(node, block, cx),
+        UASTNode::InnerBlock(ref block) => visitor.visit_inner_block(node, block, cx),
+    }
+}
+
+/// Whether `node` is an unsafe site: an unsafe block (as the `InnerBlock` node holding it) or an
+/// unsafe use. With unsafe functions, these are what baselines, diffs and reports are made of.
+pub fn is_unsafe_site(node: &Indexed<UASTNode>) -> bool {
+    match *node.item() {
+        UASTNode::InnerBlock(ref block) => block.unsaf(),
+        ref item => item.is_unsafe_use(),
+    }
+}
+
+/// The unsafe sites of `function`, in the order they are visited.
+pub fn unsafe_sites(function: &FnDecl) -> Vec<&Indexed<UASTNode>> {
+    struct Sites<'a>(Vec<&'a Indexed<UASTNode>>);
+
+    impl<'a> UastVisitor<'a> for Sites<'a> {
+        fn visit_node(&mut self, node: &'a Indexed<UASTNode>, cx: &Context<'a>) {
+            if is_unsafe_site(node) {
+                self.0.push(node);
+            }
+            walk_node(self, node, cx)
+        }
+    }
+
+    let mut sites = Sites(vec![]);
+    sites.visit_fn(function, &Context::new());
+    sites.0
+}
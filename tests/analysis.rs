@@ -0,0 +1,124 @@
+// Checks that the ports of the `unsafe.jq` analyses print exactly what jq does.
+//
+// The expected outputs were made with jq 1.6, from the repository root:
+//
+//     jq -c -L data-analysis 'include "unsafe"; .functions[] | blocks' \
+//         tests/fixtures/crates.json > tests/fixtures/blocks.json
+//     jq -c -L data-analysis 'include "unsafe"; <analysis>' \
+//         tests/fixtures/<crates or blocks>.json > tests/fixtures/expected/<analysis>.json
+//
+// and, for the analyses which print parts of UASTs, from a UAST from before the UAST was versioned:
+//
+//     jq -c -L data-analysis 'include "unsafe"; <analysis>' \
+//         tests/fixtures/encoding/old.json > tests/fixtures/expected/old/<analysis>.json
+
+extern crate unsafe_ast;
+
+use unsafe_ast::analysis::Analysis;
+use unsafe_ast::jq;
+
+const CRATES: &'static str = include_str!("fixtures/crates.json");
+const BLOCKS: &'static str = include_str!("fixtures/blocks.json");
+
+const OLD: &'static str = include_str!("fixtures/encoding/old.json");
+
+fn check(name: &str, expected: &str) {
+    let analysis = Analysis::from_name(name).expect("The analysis should exist");
+    let input = if analysis.takes_block() { BLOCKS } else { CRATES };
+    check_input(analysis, input, expected);
+}
+
+fn check_input(analysis: Analysis, input: &str, expected: &str) {
+    let output: Vec<String> = input.lines()
+        .map(|line| analysis.run(line).expect("The fixture should decode"))
+        .collect();
+    let expected: Vec<&str> = expected.lines().collect();
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn block_net_size() {
+    check("block_net_size", include_str!("fixtures/expected/block_net_size.json"));
+}
+
+#[test]
+fn block_net_size_2() {
+    check("block_net_size_2", include_str!("fixtures/expected/block_net_size_2.json"));
+}
+
+#[test]
+fn used_indices() {
+    check("used_indices", include_str!("fixtures/expected/used_indices.json"));
+}
+
+#[test]
+fn block_requirement() {
+    check("block_requirement", include_str!("fixtures/expected/block_requirement.json"));
+}
+
+#[test]
+fn unsafe_blocks_rel_sizes() {
+    check("unsafe_blocks_rel_sizes",
+          include_str!("fixtures/expected/unsafe_blocks_rel_sizes.json"));
+}
+
+#[test]
+fn closure_blocks_with_unsafe_uses() {
+    check("closure_blocks_with_unsafe_uses",
+          include_str!("fixtures/expected/closure_blocks_with_unsafe_uses.json"));
+}
+
+#[test]
+fn all_unsafe_uses_in_unsafe_declarations() {
+    check("all_unsafe_uses_in_unsafe_declarations",
+          include_str!("fixtures/expected/all_unsafe_uses_in_unsafe_declarations.json"));
+}
+
+/// Old UASTs are upgraded to be analyzed, but the parts of them which are printed are printed as
+/// they were read, as jq prints them: without the fields added since, and with their snippets
+/// still ending in '#'.
+#[test]
+fn old_uasts_print_as_read() {
+    check_input(Analysis::ClosureBlocksWithUnsafeUses, OLD,
+                include_str!("fixtures/expected/old/closure_blocks_with_unsafe_uses.json"));
+    check_input(Analysis::AllUnsafeUsesInUnsafeDeclarations, OLD,
+                include_str!("fixtures/expected/old/all_unsafe_uses_in_unsafe_declarations.json"));
+}
+
+/// The blocks of `fixtures/encoding/old.json`, a UAST from before the UAST was versioned, made
+/// with `jq -c -L data-analysis 'include "unsafe"; .functions[] | blocks'`. The expected outputs
+/// are jq's.
+#[test]
+fn old_blocks_are_upgraded() {
+    let blocks = include_str!("fixtures/encoding/old_blocks.json");
+    let cases = [("block_net_size", vec!["5", "2", "1"]),
+                 ("used_indices", vec!["[1]", "[0,1]", "[0]"]),
+                 ("block_requirement", vec!["0.5", "1", "1"])];
+    for &(name, ref expected) in cases.iter() {
+        let analysis = Analysis::from_name(name).expect("The analysis should exist");
+        let output: Vec<String> = blocks.lines()
+            .map(|line| analysis.run(line).expect("The old block should decode"))
+            .collect();
+        assert_eq!(output, *expected);
+    }
+}
+
+#[test]
+fn numbers_print_as_in_jq() {
+    let cases = [(0.0, "0"), (1.0, "1"), (0.5, "0.5"), (1.0 / 3.0, "0.3333333333333333"),
+                 (100.0 / 3.0, "33.333333333333336"), (0.0001, "0.0001"), (0.00001, "1e-05"),
+                 (1.0 / 30000.0, "3.3333333333333335e-05"), (1e16, "1e+16"),
+                 (1e15, "1000000000000000"), (123456789012345678.0, "123456789012345680"),
+                 (1e300, "1e+300"), (-2.5, "-2.5")];
+    for &(x, printed) in cases.iter() {
+        assert_eq!(jq::number(x), printed);
+    }
+}
+
+#[test]
+fn values_print_as_in_jq() {
+    let read = r#"{"b":1, "a":[1.0, 2.5e-7, "\u00e9\/\u007f\t"], "b":null, "c":{}}"#;
+    let value = jq::parse(read).expect("The value should parse");
+    assert_eq!(value.print(), r#"{"b":null,"a":[1,2.5e-07,"é/\u007f\t"],"c":{}}"#);
+    assert!(jq::parse("[1, 2] 3").is_err());
+}
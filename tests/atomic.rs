@@ -0,0 +1,38 @@
+// Checks that `write_atomically` only replaces a file once the new one is complete.
+
+extern crate unsafe_ast;
+
+mod common;
+
+use common::{read,scratch_dir};
+use unsafe_ast::atomic;
+
+use std::fs::{self,File};
+use std::io::{self,Write};
+
+#[test]
+fn replaces_the_file_when_done() {
+    let dir = scratch_dir("atomic-done");
+    let path = dir.join("out.txt");
+    File::create(&path).unwrap().write_all(b"old\n").unwrap();
+    atomic::write_atomically(&path, |out| writeln!(out, "new")).unwrap();
+    assert_eq!(read(&path), "new\n");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failed_writes_leave_the_old_file() {
+    let dir = scratch_dir("atomic-failed");
+    let path = dir.join("out.txt");
+    File::create(&path).unwrap().write_all(b"old\n").unwrap();
+    let result = atomic::write_atomically(&path, |out| {
+        try!(writeln!(out, "half"));
+        Err(io::Error::new(io::ErrorKind::Other, "interrupted"))
+    });
+    assert!(result.is_err());
+    assert_eq!(read(&path), "old\n");
+    // The temporary file is gone too
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,151 @@
+// Checks baselines of unsafe sites, with the two versions of `fixtures/diff` (see `diff.rs`).
+
+extern crate unsafe_ast;
+
+mod common;
+
+use common::load;
+use unsafe_ast::baseline::{self,Baseline};
+
+/// The snippet length of the fixtures
+const LENGTH: Option<u64> = Some(40);
+
+fn round_trip(baseline: &Baseline) -> Baseline {
+    let mut bytes = vec![];
+    baseline.write(&mut bytes).unwrap();
+    Baseline::read(&bytes[..]).unwrap()
+}
+
+#[test]
+fn fingerprints_ignore_spans_and_whitespace() {
+    assert_eq!(baseline::fnv1a(b""), 0xcbf29ce484222325);
+    assert_eq!(baseline::fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(baseline::normalize_snippet(" *p  =\n\t v "), "*p = v");
+
+    let old = load(include_str!("fixtures/diff/old.json"));
+    let reformatted = load(&include_str!("fixtures/diff/old.json")
+                                .replace("COUNTER += 1;", "COUNTER +=\\n        1;"));
+    let sites = baseline::sites(&old);
+    assert!(sites.iter().any(|site| site.snippet() == "COUNTER += 1;"));
+    let fingerprints = |sites: &[baseline::Site]| {
+        sites.iter().map(|site| site.fingerprint()).collect::<Vec<_>>()
+    };
+    assert_eq!(fingerprints(&sites), fingerprints(&baseline::sites(&reformatted)));
+
+    // `read_raw` is five lines further down in the new version, but otherwise the same
+    let new = load(include_str!("fixtures/diff/new.json"));
+    let read_raw = |sites: Vec<baseline::Site>| {
+        sites.into_iter().filter(|site| site.function() == "read_raw").collect::<Vec<_>>()
+    };
+    let (before, after) = (read_raw(sites), read_raw(baseline::sites(&new)));
+    assert!(!before.is_empty());
+    assert!(before[0].span() != after[0].span());
+    assert_eq!(fingerprints(&before), fingerprints(&after));
+}
+
+#[test]
+fn check_reports_new_and_gone_sites() {
+    let old = load(include_str!("fixtures/diff/old.json"));
+    let new = load(include_str!("fixtures/diff/new.json"));
+    let base = round_trip(&Baseline::new(&baseline::sites(&old), LENGTH, None));
+    assert_eq!(base, Baseline::new(&baseline::sites(&old), LENGTH, None));
+
+    let old_sites = baseline::sites(&old);
+    let check = base.check(&old_sites, LENGTH).unwrap();
+    assert!(check.new_sites().is_empty());
+    assert!(check.gone().is_empty());
+    assert_eq!(check.unreviewed().len(), base.entries().len());
+
+    let new_sites = baseline::sites(&new);
+    let check = base.check(&new_sites, LENGTH).unwrap();
+    let added: Vec<(&str, &str, &str)> = check.new_sites().iter()
+        .map(|site| (site.kind(), site.function(), site.snippet()))
+        .collect();
+    assert_eq!(added, vec![("Deref", "Wrapper::get", "*self.p"),
+                           ("UnsafeFn", "write_raw", ""),
+                           ("Deref", "write_raw", "*p = v")]);
+    let gone: Vec<(&str, &str)> = check.gone().iter()
+        .map(|entry| (entry.kind(), entry.function()))
+        .collect();
+    assert_eq!(gone, vec![("ExternStatic", "Wrapper::get"),
+                          ("UnsafeFn", "legacy_read"),
+                          ("Deref", "legacy_read")]);
+}
+
+#[test]
+fn review_notes_are_kept() {
+    let old = load(include_str!("fixtures/diff/old.json"));
+    let new = load(include_str!("fixtures/diff/new.json"));
+    let mut text = vec![];
+    Baseline::new(&baseline::sites(&old), LENGTH, None).write(&mut text).unwrap();
+    // Review every site in `Wrapper::get`
+    let text: String = String::from_utf8(text).unwrap().lines()
+        .map(|line| if line.contains("\tWrapper::get\t") {
+            format!("{}reviewed by aozdemir\n", line)
+        } else {
+            format!("{}\n", line)
+        })
+        .collect();
+    let base = Baseline::read(text.as_bytes()).unwrap();
+    let new_sites = baseline::sites(&new);
+    let check = base.check(&new_sites, LENGTH).unwrap();
+    assert!(check.unreviewed().iter().all(|entry| entry.function() != "Wrapper::get"));
+
+    let updated = round_trip(&Baseline::new(&new_sites, LENGTH, Some(&base)));
+    let notes: Vec<(&str, &str, Option<&str>)> = updated.entries().iter()
+        .filter(|entry| entry.function() == "Wrapper::get")
+        .map(|entry| (entry.kind(), entry.snippet(), entry.note()))
+        .collect();
+    assert!(notes.contains(&("Call", "libc::abort()", Some("reviewed by aozdemir"))));
+    assert!(notes.contains(&("Deref", "*self.p", None)));
+    assert!(updated.check(&new_sites, LENGTH).unwrap().new_sites().is_empty());
+    assert!(Baseline::read(&b"not a baseline\n"[..]).is_err());
+}
+
+#[test]
+fn statements_tell_apart_sites_with_the_same_snippet() {
+    let old = include_str!("fixtures/diff/old.json");
+    // `errno` becomes a second `COUNTER += 1;`, in the next statement
+    let doubled = load(&old.replace(r#""snippet":"errno""#, r#""snippet":"COUNTER += 1;""#)
+                           .replace(r#""item":"ExternStatic""#, r#""item":"MutStatic""#));
+    let sites = baseline::sites(&doubled);
+    let counters: Vec<(&str, u64)> = sites.iter()
+        .filter(|site| site.snippet() == "COUNTER += 1;")
+        .map(|site| (site.statement(), site.fingerprint()))
+        .collect();
+    assert_eq!(counters.len(), 2);
+    assert_eq!((counters[0].0, counters[1].0), ("1.0", "1.1"));
+    assert!(counters[0].1 != counters[1].1);
+
+    // So a site which moves to another statement is new, even with the same snippet
+    let base = Baseline::new(&baseline::sites(&load(old)), LENGTH, None);
+    let moved = baseline::sites(&load(&old.replacen(r#""index":5,"#, r#""index":6,"#, 1)));
+    let check = base.check(&moved, LENGTH).unwrap();
+    let added: Vec<(&str, &str)> = check.new_sites().iter()
+        .map(|site| (site.statement(), site.snippet()))
+        .collect();
+    assert_eq!(added, vec![("1.6", "libc::abort()")]);
+    assert_eq!(check.gone().iter().map(|entry| entry.statement()).collect::<Vec<_>>(),
+               vec!["1.5"]);
+}
+
+#[test]
+fn snippet_lengths_must_match() {
+    let old = load(include_str!("fixtures/diff/old.json"));
+    let sites = baseline::sites(&old);
+    assert_eq!(baseline::snippet_length(&[old.clone()]), Ok(LENGTH));
+    let base = round_trip(&Baseline::new(&sites, LENGTH, None));
+    assert_eq!(base.snippet_length(), LENGTH);
+    assert!(base.check(&sites, Some(80)).is_err());
+    assert!(base.check(&sites, None).is_err());
+    assert_eq!(round_trip(&Baseline::new(&sites, None, None)).snippet_length(), None);
+
+    let unlimited = load(&include_str!("fixtures/diff/new.json")
+                              .replace(r#""snippet_length":40"#, r#""snippet_length":null"#));
+    assert_eq!(baseline::snippet_length(&[unlimited.clone()]), Ok(None));
+    assert!(baseline::snippet_length(&[old, unlimited]).is_err());
+
+    // Baselines from before snippet lengths were recorded have other fingerprints
+    let first = "# unsafe-ast baseline 1\n0123456789abcdef\tlegacy\tDeref\tread\t*p\t\n";
+    assert!(Baseline::read(first.as_bytes()).is_err());
+}
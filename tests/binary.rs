@@ -0,0 +1,68 @@
+// Checks that the binary encoding of UASTs round-trips, and rejects corrupt records.
+
+extern crate unsafe_ast;
+
+use unsafe_ast::Crate;
+use unsafe_ast::binary::{self,DecodeError};
+
+const CRATES: &'static str = include_str!("fixtures/crates.json");
+
+fn crates() -> Vec<Crate> {
+    CRATES.lines()
+        .map(|line| unsafe_ast::decode(line).expect("The fixture should decode"))
+        .collect()
+}
+
+#[test]
+fn records_round_trip() {
+    for krate in crates() {
+        let record = binary::encode(&krate);
+        assert_eq!(binary::decode(&record).expect("The record should decode"), krate);
+    }
+}
+
+#[test]
+fn files_round_trip() {
+    let crates = crates();
+    let mut file = vec![];
+    binary::write_header(&mut file).unwrap();
+    for krate in &crates {
+        binary::write_record(&mut file, &binary::encode(krate)).unwrap();
+    }
+    assert!(binary::is_binary(&file));
+    assert!(file.len() < CRATES.len() / 2);
+
+    let decoded: Vec<Crate> = binary::records(&file[..]).unwrap()
+        .map(|record| binary::decode(&record.unwrap()).unwrap())
+        .collect();
+    assert_eq!(decoded, crates);
+}
+
+#[test]
+fn rejects_corrupt_input() {
+    let record = binary::encode(&crates()[0]);
+    match binary::decode(&record[..record.len() - 1]) {
+        Err(DecodeError::UnexpectedEnd) => {},
+        other => panic!("Expected the end of the record, got {:?}", other.map(|_| ())),
+    }
+    let mut longer = record.clone();
+    longer.push(0);
+    match binary::decode(&longer) {
+        Err(DecodeError::TrailingBytes(1)) => {},
+        other => panic!("Expected a trailing byte, got {:?}", other.map(|_| ())),
+    }
+    match binary::records(CRATES.as_bytes()) {
+        Err(DecodeError::NotBinary) => {},
+        _ => panic!("JSON is not a binary UAST file"),
+    }
+
+    let mut file = vec![];
+    binary::write_header(&mut file).unwrap();
+    binary::write_record(&mut file, &record).unwrap();
+    file.pop();
+    let mut records = binary::records(&file[..]).unwrap();
+    match records.next() {
+        Some(Err(DecodeError::UnexpectedEnd)) => {},
+        _ => panic!("Expected the end of the file"),
+    }
+}
@@ -0,0 +1,32 @@
+// Helpers shared by the integration tests
+//
+// Each test file which needs them declares `mod common;`, and few need all of them.
+
+#![allow(dead_code)]
+
+use unsafe_ast::{self,Crate};
+
+use std::env;
+use std::fs::{self,File};
+use std::io::Read;
+use std::path::{Path,PathBuf};
+
+/// Decodes a UAST fixture, allowing for the newline at the end of the file.
+pub fn load(json: &str) -> Crate {
+    unsafe_ast::decode(json.trim()).expect("The fixture should decode")
+}
+
+/// A fresh, empty directory for the test `name` to write to.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("unsafe-ast-test-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn read(path: &Path) -> String {
+    let mut contents = String::new();
+    File::open(path).and_then(|mut f| f.read_to_string(&mut contents))
+        .expect(&format!("Could not read {}", path.display()));
+    contents
+}
@@ -0,0 +1,63 @@
+// Checks what `diff` reports between two versions of a crate.
+//
+// `fixtures/diff/new.json` is `old.json` with every span moved down five lines, `legacy_read`
+// removed, `write_raw` added, and, in the unsafe block of `Wrapper::get`, `errno` replaced by a
+// raw pointer deref and `libc::abort` now called from a macro.
+
+extern crate unsafe_ast;
+
+mod common;
+
+use common::load;
+use unsafe_ast::diff::{Change,Counts,CrateDiff};
+
+#[test]
+fn reports_unsafe_changes() {
+    let old = load(include_str!("fixtures/diff/old.json"));
+    let new = load(include_str!("fixtures/diff/new.json"));
+    let diff = CrateDiff::new(&old, &new);
+
+    let names: Vec<(&str, &str)> = diff.functions().iter()
+        .map(|f| (f.name(), f.function().name()))
+        .collect();
+    assert_eq!(names, vec![("Wrapper::get", "changed"),
+                           ("legacy_read", "removed"),
+                           ("write_raw", "added")]);
+    // `read_raw` only moved, so isn't reported at all
+
+    let get = &diff.functions()[0];
+    assert_eq!(get.blocks().len(), 1);
+    let uses: Vec<(&str, &str)> = get.uses().iter()
+        .map(|change| (change.name(), change.after().or(change.before()).unwrap().item().kind()))
+        .collect();
+    assert_eq!(uses, vec![("removed", "ExternStatic"), ("changed", "Call"), ("added", "Deref")]);
+    match get.uses()[1] {
+        Change::Changed(old, new) => {
+            assert_eq!(old.snippet(), new.snippet());
+            assert!(old.macro_origin() != new.macro_origin());
+        },
+        ref other => panic!("Expected the call to abort to have changed, not {:?}", other),
+    }
+
+    assert_eq!(diff.unsafe_function_counts(), Counts { added: 1, removed: 1, changed: 0 });
+    assert_eq!(diff.unsafe_block_counts(), Counts { added: 0, removed: 0, changed: 1 });
+    assert_eq!(diff.unsafe_use_counts(), Counts { added: 2, removed: 2, changed: 1 });
+
+    let json = diff.to_json();
+    assert_eq!(json.find_path(&["new", "version"]).and_then(|v| v.as_string()), Some("0.2.0"));
+    assert_eq!(json.find_path(&["summary", "uses", "added"]).and_then(|n| n.as_u64()), Some(2));
+    assert!(diff.to_text().contains("+ unsafe fn write_raw\n"));
+}
+
+#[test]
+fn reports_changes_of_unsafety() {
+    let old = load(include_str!("fixtures/diff/old.json"));
+    // `read_raw` is the first unsafe thing
+    let new = load(&include_str!("fixtures/diff/old.json").replacen(r#""unsaf":true"#,
+                                                                     r#""unsaf":false"#, 1));
+    let diff = CrateDiff::new(&old, &new);
+    assert_eq!(diff.functions().len(), 1);
+    assert_eq!(diff.functions()[0].unsafety(), (Some(true), Some(false)));
+    assert_eq!(diff.unsafe_function_counts(), Counts { added: 0, removed: 1, changed: 0 });
+    assert!(diff.to_text().contains("~ fn read_raw (no longer unsafe)\n"));
+}
@@ -0,0 +1,93 @@
+// Checks that records from before the UAST was versioned are upgraded, and that the tagged
+// encoding converts back to the default one.
+//
+// `fixtures/encoding/old.json` is written like the first emitters wrote UASTs: no metadata,
+// signatures or visibilities, calls with just their safety and whether they are FFI, and long
+// snippets cut down to 39 characters and a '#'. `fixtures/encoding/tagged.json` was made from the
+// repository root with
+//
+//     jq -c -f data-analysis/to-tagged.jq tests/fixtures/crates.json \
+//         > tests/fixtures/encoding/tagged.json
+
+extern crate rustc_serialize;
+extern crate unsafe_ast;
+
+mod common;
+
+use common::load;
+use rustc_serialize::json::Json;
+use unsafe_ast::{UASTNode,Visibility};
+use unsafe_ast::encoding;
+
+const OLD: &'static str = include_str!("fixtures/encoding/old.json");
+const CRATES: &'static str = include_str!("fixtures/crates.json");
+const TAGGED: &'static str = include_str!("fixtures/encoding/tagged.json");
+
+fn parse(json: &str) -> Json {
+    Json::from_str(json).expect("The fixture should be JSON")
+}
+
+#[test]
+fn old_records_are_upgraded() {
+    let krate = load(OLD);
+    assert_eq!(krate.meta().schema_version(), 0);
+    assert_eq!(krate.meta().encoding(), "default");
+    assert!(krate.unsafe_impls().is_empty() && krate.module_tree().is_none());
+
+    let poke = &krate.functions()[0];
+    assert_eq!(*poke.vis(), Visibility::Inherited);
+    assert!(!poke.exported() && poke.sig().inputs().is_empty() && poke.span_info().is_none());
+
+    let block = poke.block().contents()[0].item().block().expect("An unsafe block");
+    let write = &block.contents()[0];
+    assert_eq!(write.snippet(), "libc::write(fd, buf.as_ptr() as *const ");
+    assert!(write.truncated());
+    match *write.item() {
+        UASTNode::Call(ref unsaf, ref ffi, ref callee) => {
+            assert!(unsaf.unsaf() && ffi.is_ffi() && !ffi.is_intrinsic());
+            assert_eq!(ffi.abi(), "");
+            assert_eq!((callee.path(), callee.krate()), (None, None));
+        },
+        ref other => panic!("Expected a call, found {:?}", other),
+    }
+    assert_eq!(block.contents()[1].snippet(), "*p");
+    assert!(!block.contents()[1].truncated());
+
+    // Blocks inside closures are upgraded too, and short snippets are left alone
+    let closure = &poke.block().contents()[1];
+    assert_eq!(closure.snippet(), "let f = || unsafe { libc::abort() };");
+    assert!(!closure.truncated());
+    let abort = &closure.item().block().expect("A closure").contents()[0];
+    assert_eq!(abort.item().kind(), "Call");
+}
+
+#[test]
+fn tagged_records_untag_to_the_default_encoding() {
+    for (tagged, default) in TAGGED.lines().zip(CRATES.lines()) {
+        let tagged = parse(tagged);
+        assert_eq!(tagged.find_path(&["meta", "encoding"]).and_then(Json::as_string),
+                   Some("tagged"));
+        assert_eq!(encoding::untag(tagged.clone()), parse(default));
+        assert_eq!(encoding::tag(parse(default)), tagged);
+        assert_eq!(load(&tagged.to_string()), load(default));
+    }
+    assert_eq!(TAGGED.lines().count(), CRATES.lines().count());
+}
+
+#[test]
+fn tagged_old_records_decode_like_untagged_ones() {
+    let tagged = encoding::tag(parse(OLD));
+    assert_eq!(load(&tagged.to_string()), load(OLD));
+}
+
+#[test]
+fn tagging_twice_is_tagging_once() {
+    for line in TAGGED.lines().chain(OLD.lines()) {
+        let once = encoding::tag(parse(line));
+        assert_eq!(encoding::tag(once.clone()), once);
+    }
+    // A dataset which is partly converted
+    for (tagged, default) in TAGGED.lines().zip(CRATES.lines()) {
+        assert_eq!(encoding::tag(parse(default)), encoding::tag(parse(tagged)));
+    }
+}
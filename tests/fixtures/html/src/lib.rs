@@ -0,0 +1,26 @@
+// The crate of `tests/fixtures/html/crate.json`, whose hand-written spans match it exactly
+
+#[macro_use]
+extern crate lazy_static;
+extern crate libc;
+
+mod raw;
+
+static mut PAGES: usize = 0;
+
+lazy_static! {
+    static ref PAGE: usize = page_size();
+}
+
+/// The size of a page, in bytes.
+pub fn page_size() -> usize {
+    unsafe {
+        PAGES += 1;
+        libc::sysconf(libc::_SC_PAGESIZE) as usize
+    }
+}
+
+/// Reads the first byte of a page.
+pub unsafe fn first_byte(page: *const u8) -> u8 {
+    *page
+}
@@ -0,0 +1,13 @@
+// Raw memory, for `tests/fixtures/html/src/lib.rs`
+
+use libc;
+
+/// Zeroes `len` bytes at `p`.
+pub unsafe fn zero(p: *mut u8, len: usize) {
+    libc::memset(p as *mut libc::c_void, 0, len);
+}
+
+/// Whether the last call failed with `EINTR`.
+pub fn interrupted() -> bool {
+    unsafe { *libc::__errno_location() == libc::EINTR }
+}
@@ -0,0 +1,155 @@
+// Checks the HTML report of `fixtures/html/crate.json`, whose source is in `fixtures/html`, and
+// the parsing of spans which it relies on.
+//
+// The crate has a file of its own, `src/lib.rs`, and a module, `src/raw/mod.rs`, each with an
+// unsafe function and an unsafe block, and an unsafe block from `lazy_static!`, which isn't in
+// either. A copy of it with `src/raw/mod.rs` renamed to reach outside the source checks that
+// such files aren't read.
+
+extern crate unsafe_ast;
+
+mod common;
+
+use common::{read,scratch_dir};
+use unsafe_ast::html::{self,Class,Report};
+use unsafe_ast::span::Span;
+
+use std::fs;
+use std::path::Path;
+
+fn report() -> Report {
+    Report::new(&[common::load(include_str!("fixtures/html/crate.json"))])
+}
+
+#[test]
+fn parses_spans() {
+    assert_eq!(Span::parse("src/lib.rs:13:5: 13:7"),
+               Some(Span::new("src/lib.rs".to_string(), 13, 5, 13, 7)));
+    assert_eq!(Span::parse(r"C:\src\lib.rs:1:2: 3:4"),
+               Some(Span::new(r"C:\src\lib.rs".to_string(), 1, 2, 3, 4)));
+    let external = Span::parse("<std macros>:1:1: 1:33").unwrap();
+    assert!(!external.is_real());
+    let within = |file: &str| Span::new(file.to_string(), 1, 1, 1, 2).is_within_source();
+    assert!(within("src/lib.rs") && within("./src/lib.rs") && within(r"src\lib.rs"));
+    assert!(!within("/home/me/.cargo/registry/src/libc-0.2.17/src/lib.rs"));
+    assert!(!within("../libc/src/lib.rs") && !within("src/../../libc/src/lib.rs"));
+    assert!(!within(r"C:\src\lib.rs") && !within(r"\\server\src\lib.rs"));
+    assert!(Span::parse("src/lib.rs:13:5").is_none());
+    assert!(Span::parse(":13:5: 13:7").is_none());
+    assert!(Span::parse("src/lib.rs:x:5: 13:7").is_none());
+
+    let span = Span::parse("src/lib.rs:22:9: 29:10").unwrap();
+    assert!(span.contains(22, 9) && span.contains(25, 1) && span.contains(29, 9));
+    assert!(!span.contains(22, 8) && !span.contains(29, 10));
+}
+
+#[test]
+fn collects_sites_in_source_order() {
+    let report = report();
+    let sites: Vec<_> = report.sites().iter()
+        .map(|site| (site.class(), site.label(), site.span().map(|s| (s.file(), s.lo_line()))))
+        .collect();
+    assert_eq!(sites, vec![
+        (Class::UnsafeBlock, "unsafe block", Some(("src/lib.rs", 17))),
+        (Class::UnsafeUse, "MutStatic", Some(("src/lib.rs", 18))),
+        (Class::UnsafeUse, "Call", Some(("src/lib.rs", 19))),
+        (Class::UnsafeFn, "unsafe fn", Some(("src/lib.rs", 24))),
+        (Class::UnsafeUse, "Deref", Some(("src/lib.rs", 25))),
+        (Class::UnsafeFn, "unsafe fn", Some(("src/raw/mod.rs", 6))),
+        (Class::UnsafeUse, "Call", Some(("src/raw/mod.rs", 7))),
+        (Class::UnsafeBlock, "unsafe block", Some(("src/raw/mod.rs", 12))),
+        // The deref starts before the call it dereferences the result of
+        (Class::UnsafeUse, "Deref", Some(("src/raw/mod.rs", 12))),
+        (Class::UnsafeUse, "Call", Some(("src/raw/mod.rs", 12))),
+        // In `lazy_static!`, so nowhere in the source
+        (Class::UnsafeBlock, "unsafe block", None),
+        (Class::UnsafeUse, "MutStatic", None),
+    ]);
+    assert!(report.sites().iter().enumerate().all(|(n, site)| site.id() == n + 1));
+    let pages: Vec<(&str, &str)> = report.pages().iter().map(|(f, p)| (&f[..], &p[..])).collect();
+    assert_eq!(pages, vec![("src/lib.rs", "src_lib.rs.html"),
+                           ("src/raw/mod.rs", "src_raw_mod.rs.html")]);
+}
+
+#[test]
+fn writes_annotated_source() {
+    let report = report();
+    let dir = scratch_dir("html-annotated");
+    let missing = report.write(Path::new("tests/fixtures/html"), &dir).unwrap();
+    assert!(missing.is_empty());
+
+    let index = read(&dir.join("index.html"));
+    assert!(index.contains("<title>Unsafe code in pager</title>"));
+    assert!(index.contains("<p>2 unsafe functions, 3 unsafe blocks and 7 unsafe uses.</p>"));
+    assert!(index.contains("<a href=\"src_raw_mod.rs.html\">src/raw/mod.rs</a></td>\
+                            <td class=\"count\">1</td><td class=\"count\">1</td>\
+                            <td class=\"count\">3</td>"));
+    assert!(index.contains("<a href=\"src_raw_mod.rs.html#site-9\">line 12</a>: \
+                            Deref in pager::raw::interrupted \
+                            <code>*libc::__errno_location()</code>"));
+    assert!(index.contains("<h2>Outside the crate's source</h2>\n<ul>\n\
+                            <li>unsafe block in pager::PAGE::deref <code>unsafe {</code></li>\n\
+                            <li>MutStatic in pager::PAGE::deref <code>LAZY</code></li>\n"));
+
+    let lib = read(&dir.join("src_lib.rs.html"));
+    assert!(lib.contains("<span id=\"L17\"><span class=\"ln\">17</span>    \
+                          <span id=\"site-1\"></span><span class=\"unsafe-block\" \
+                          title=\"unsafe block in pager::page_size\">unsafe {</span></span>"));
+    assert!(lib.contains("<span id=\"site-2\"></span><span class=\"unsafe-block unsafe-use\" \
+                          title=\"MutStatic in pager::page_size\">PAGES</span>\
+                          <span class=\"unsafe-block\" title=\"unsafe block in \
+                          pager::page_size\"> += 1;</span>"));
+    assert!(lib.contains("<span id=\"site-5\"></span><span class=\"unsafe-fn unsafe-use\" \
+                          title=\"Deref in pager::first_byte\">*page</span>"));
+    // Every line of the source is there
+    assert!(lib.contains("<span class=\"ln\">26</span>"));
+    assert!(!lib.contains("<span class=\"ln\">27</span>"));
+
+    // The innermost site names each piece of a nested one
+    let raw = read(&dir.join("src_raw_mod.rs.html"));
+    assert!(raw.contains("<span id=\"site-9\"></span><span class=\"unsafe-block unsafe-use\" \
+                          title=\"Deref in pager::raw::interrupted\">*</span>\
+                          <span id=\"site-10\"></span><span class=\"unsafe-block unsafe-use\" \
+                          title=\"Call in pager::raw::interrupted\">\
+                          libc::__errno_location()</span>"));
+    assert!(raw.contains("<span id=\"L5\"><span class=\"ln\">5</span>/// Zeroes `len` bytes at \
+                          `p`.</span>"));
+}
+
+#[test]
+fn reports_missing_sources() {
+    let report = report();
+    let dir = scratch_dir("html-missing");
+    let missing = report.write(Path::new("tests/fixtures/no-such-crate"), &dir).unwrap();
+    assert_eq!(missing, vec!["src/lib.rs".to_string(), "src/raw/mod.rs".to_string()]);
+    let index = read(&dir.join("index.html"));
+    assert!(index.contains("src/raw/mod.rs (source not found)"));
+    assert!(index.contains("<li>line 12: Deref in pager::raw::interrupted"));
+    assert!(!index.contains("href=\"src_"));
+    assert!(!dir.join("src_lib.rs.html").exists());
+    assert_eq!(html::escape("<a href='x'>&</a>"), "&lt;a href=&#39;x&#39;&gt;&amp;&lt;/a&gt;");
+}
+
+#[test]
+fn does_not_read_outside_the_source() {
+    for file in &["../html/src/raw/mod.rs", "/etc/raw/mod.rs"] {
+        let json = include_str!("fixtures/html/crate.json").replace("src/raw/mod.rs", file);
+        let report = Report::new(&[common::load(&json)]);
+        let pages: Vec<&str> = report.pages().keys().map(|file| &file[..]).collect();
+        assert_eq!(pages, vec!["src/lib.rs"]);
+        let dir = scratch_dir("html-outside");
+        let missing = report.write(Path::new("tests/fixtures/html"), &dir).unwrap();
+        assert!(missing.is_empty());
+        let index = read(&dir.join("index.html"));
+        assert!(index.contains("<h2>Outside the crate's source</h2>"));
+        assert!(index.contains("<li>unsafe fn in pager::raw::zero</li>\n"));
+        assert!(!index.contains(file));
+        assert!(!dir.join("html_src_raw_mod.rs.html").exists());
+        // Nothing is left half-written
+        let mut names: Vec<_> = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["index.html", "src_lib.rs.html"]);
+    }
+}
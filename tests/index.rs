@@ -0,0 +1,99 @@
+// Checks that indexes of UAST files find the right crates, and notice when they are stale.
+
+extern crate unsafe_ast;
+
+mod common;
+
+use unsafe_ast::binary;
+use unsafe_ast::index::Index;
+
+use std::fs::{File,OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+const CRATES: &'static str = include_str!("fixtures/crates.json");
+
+/// Writes `contents` to a fresh file in a fresh directory.
+fn data_file(name: &str, contents: &[u8]) -> PathBuf {
+    let path = common::scratch_dir(&format!("index-{}", name)).join(name);
+    File::create(&path).unwrap().write_all(contents).unwrap();
+    path
+}
+
+fn check_lookups(data: &PathBuf) {
+    let index = Index::build(data).unwrap();
+    index.write(data).unwrap();
+    let index = Index::read(data).unwrap();
+    assert_eq!(index, Index::build(data).unwrap());
+    assert_eq!(index.entries().len(), CRATES.lines().count());
+
+    let mut file = File::open(data).unwrap();
+    for (line, entry) in CRATES.lines().zip(index.entries()) {
+        let expected = unsafe_ast::decode(line).unwrap();
+        assert_eq!(index.find(expected.name(), None), vec![entry]);
+        assert_eq!(index.find(expected.name(), expected.meta().version()), vec![entry]);
+        assert_eq!(index.load(&mut file, entry).unwrap(), expected);
+    }
+    assert!(index.find("sample", Some("0.0.0")).is_empty());
+    assert!(index.find("no_such_crate", None).is_empty());
+}
+
+#[test]
+fn indexes_json_lines() {
+    // With a blank line, which isn't indexed
+    let data = data_file("crates.json", format!("\n{}", CRATES).as_bytes());
+    check_lookups(&data);
+
+    let index = Index::read(&data).unwrap();
+    let mut file = File::open(&data).unwrap();
+    let entry = &index.entries()[0];
+    assert_eq!(index.raw(&mut file, entry).unwrap(), CRATES.lines().next().unwrap().as_bytes());
+}
+
+#[test]
+fn indexes_binary_files() {
+    let mut contents = vec![];
+    binary::write_header(&mut contents).unwrap();
+    for line in CRATES.lines() {
+        let record = binary::encode(&unsafe_ast::decode(line).unwrap());
+        binary::write_record(&mut contents, &record).unwrap();
+    }
+    let data = data_file("crates.bin", &contents);
+    check_lookups(&data);
+    assert!(Index::read(&data).unwrap().is_binary());
+}
+
+#[test]
+fn stale_indexes_are_rejected() {
+    let data = data_file("stale.json", CRATES.as_bytes());
+    Index::build(&data).unwrap().write(&data).unwrap();
+    OpenOptions::new().append(true).open(&data).unwrap().write_all(b"\n").unwrap();
+    assert!(Index::read(&data).is_err());
+}
+
+#[test]
+fn changes_which_keep_the_length_are_noticed() {
+    let data = data_file("same-length.json", CRATES.as_bytes());
+    Index::build(&data).unwrap().write(&data).unwrap();
+    File::create(&data).unwrap().write_all(CRATES.replace("sample", "simple").as_bytes()).unwrap();
+    assert!(Index::read(&data).is_err());
+}
+
+#[test]
+fn loads_check_the_crate_name() {
+    let data = data_file("names.json", CRATES.as_bytes());
+    Index::build(&data).unwrap().write(&data).unwrap();
+    // An index which is wrong about which crate is where, but otherwise up to date
+    let path = Index::path(&data);
+    let text = common::read(&path).replacen("\nsample\t", "\nnested\t", 1);
+    File::create(&path).unwrap().write_all(text.as_bytes()).unwrap();
+
+    let index = Index::read(&data).unwrap();
+    let found = index.find("nested", None);
+    assert_eq!(found.len(), 2);
+    let mut file = File::open(&data).unwrap();
+    assert!(index.line(&mut file, found[0]).is_err());
+    assert!(index.load(&mut file, found[0]).is_err());
+    assert_eq!(index.load(&mut file, found[1]).unwrap().name(), "nested");
+}
+
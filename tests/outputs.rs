@@ -0,0 +1,122 @@
+// Checks that `analyze process` writes exactly the files `initial-analysis.sh` makes with jq.
+//
+// The expected outputs were made by running `run_jq_programs_if_needed` (jq 1.6) with
+// `all_data=tests/fixtures/crates.json`, and those in `expected/old/outputs` with
+// `all_data=tests/fixtures/encoding/old.json`, a UAST from before the UAST was versioned.
+
+extern crate unsafe_ast;
+
+mod common;
+
+use common::{read,scratch_dir};
+use unsafe_ast::jq;
+use unsafe_ast::outputs::{self,Output};
+
+use std::env;
+use std::fs::{self,File};
+use std::io::Write;
+use std::path::{Path,PathBuf};
+use std::process::Command;
+
+const CRATES: &'static str = include_str!("fixtures/crates.json");
+const OLD: &'static str = include_str!("fixtures/encoding/old.json");
+
+fn fixture(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+}
+
+fn expected(output: Output) -> String {
+    read(&fixture("expected/outputs").join(output.file_name()))
+}
+
+fn expected_old(output: Output) -> String {
+    read(&fixture("expected/old/outputs").join(output.file_name()))
+}
+
+/// The file `output` for the UASTs in `input`, computed line by line.
+fn lines(output: Output, input: &str) -> String {
+    let mut lines = String::new();
+    for line in input.lines() {
+        let krate = unsafe_ast::decode(line).expect("The fixture should decode");
+        let read = jq::parse(line).expect("The fixture should parse");
+        for line in output.lines(&krate, &jq::Printer::as_read(&krate, &read)) {
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+    }
+    lines
+}
+
+/// The `analyze` binary, which cargo builds before running the tests.
+fn analyze() -> PathBuf {
+    // The test binary is in target/<profile>/deps, and `analyze` in target/<profile>
+    let exe = env::current_exe().unwrap();
+    let analyze = exe.parent().and_then(|deps| deps.parent()).unwrap().join("analyze");
+    assert!(analyze.exists(), "cargo should have built {}", analyze.display());
+    analyze
+}
+
+#[test]
+fn lines_match_jq() {
+    for &output in outputs::OUTPUTS {
+        assert_eq!(lines(output, CRATES), expected(output), "{}", output.file_name());
+    }
+}
+
+/// Old UASTs are upgraded to be analyzed, but the nodes and blocks in `unsafe_uses*.json`,
+/// `unsafe_contexts.json` and `closures.json` are written as they were read, as jq writes them.
+#[test]
+fn old_lines_match_jq() {
+    for &output in outputs::OUTPUTS {
+        assert_eq!(lines(output, OLD), expected_old(output), "{}", output.file_name());
+    }
+}
+
+#[test]
+fn process_writes_all_outputs() {
+    let dir = scratch_dir("process");
+    let status = Command::new(analyze())
+        .arg("process").arg("-j").arg("3").arg("-o").arg(&dir).arg(fixture("crates.json"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    for &output in outputs::OUTPUTS {
+        assert_eq!(read(&dir.join(output.file_name())), expected(output), "{}", output.file_name());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn process_writes_old_uasts_as_read() {
+    let dir = scratch_dir("process-old");
+    let status = Command::new(analyze())
+        .arg("process").arg("-o").arg(&dir).arg(fixture("encoding/old.json"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    for &output in outputs::OUTPUTS {
+        assert_eq!(read(&dir.join(output.file_name())), expected_old(output),
+                   "{}", output.file_name());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn process_fails_on_a_bad_uast() {
+    let dir = scratch_dir("process-bad");
+    let input = dir.join("json.out");
+    // More UASTs after the bad one than the workers' queue holds, so that reading has to stop
+    let rest: Vec<&str> = (0..100).map(|_| CRATES).collect();
+    File::create(&input)
+        .and_then(|mut f| write!(f, "{}{{\"name\": \"broken\"}}\n{}", CRATES, rest.concat()))
+        .unwrap();
+    let output = Command::new(analyze())
+        .arg("process").arg("-j").arg("2").arg("-o").arg(&dir).arg(&input)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 5"));
+    // Nothing is moved into place, and the temporary files are cleaned up
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,111 @@
+// Checks the SARIF log of the unsafe sites of `fixtures/sarif/crate.json`.
+//
+// In the fixture, `uid` calls `getuid` in an unsafe block, and the unsafe `sys::env::var` uses a
+// mutable static, dereferences `environ` in a macro from `libc`, whose source is outside the
+// crate, and dereferences a pointer in `debug_assert!`, which has no source at all.
+
+extern crate rustc_serialize;
+extern crate unsafe_ast;
+
+mod common;
+
+use common::load;
+use rustc_serialize::json::Json;
+use unsafe_ast::baseline;
+use unsafe_ast::sarif;
+
+const CRATE: &'static str = include_str!("fixtures/sarif/crate.json");
+
+fn get<'a>(json: &'a Json, path: &[&str]) -> &'a Json {
+    json.find_path(path).unwrap_or_else(|| panic!("{:?} should be in {}", path, json))
+}
+
+fn str_at<'a>(json: &'a Json, path: &[&str]) -> &'a str {
+    get(json, path).as_string().expect("Should be a string")
+}
+
+fn u64_at(json: &Json, path: &[&str]) -> u64 {
+    get(json, path).as_u64().expect("Should be a number")
+}
+
+#[test]
+fn logs_each_unsafe_site() {
+    let krate = load(CRATE);
+    let log = sarif::log(&[krate.clone()], None);
+    assert_eq!(str_at(&log, &["version"]), "2.1.0");
+    let run = &get(&log, &["runs"]).as_array().unwrap()[0];
+    assert_eq!(str_at(run, &["columnKind"]), "unicodeCodePoints");
+    assert!(run.find("originalUriBaseIds").is_none());
+
+    let rules = get(run, &["tool", "driver", "rules"]).as_array().unwrap();
+    let results = get(run, &["results"]).as_array().unwrap();
+    let sites = baseline::sites(&krate);
+    assert_eq!(results.len(), 6);
+    assert_eq!(results.len(), sites.len());
+    for (result, site) in results.iter().zip(&sites) {
+        let rule = &rules[u64_at(result, &["ruleIndex"]) as usize];
+        assert_eq!(str_at(result, &["ruleId"]), site.kind());
+        assert_eq!(str_at(rule, &["id"]), site.kind());
+        assert_eq!(str_at(result, &["partialFingerprints", "unsafeAstSite/v1"]),
+                   format!("{:016x}", site.fingerprint()));
+        assert_eq!(str_at(result, &["properties", "macroOrigin"]),
+                   format!("{:?}", site.macro_origin()));
+    }
+    let rule_ids: Vec<&str> = results.iter().map(|r| str_at(r, &["ruleId"])).collect();
+    assert_eq!(rule_ids, vec!["UnsafeBlock", "Call", "UnsafeFn", "MutStatic", "Deref", "Deref"]);
+
+    let location = |n: usize| &get(&results[n], &["locations"]).as_array().unwrap()[0];
+    let region = |n: usize| {
+        let region = get(location(n), &["physicalLocation", "region"]);
+        ["startLine", "startColumn", "endLine", "endColumn"].iter()
+            .map(|key| u64_at(region, &[key]))
+            .collect::<Vec<_>>()
+    };
+    let artifact = |n: usize| get(location(n), &["physicalLocation", "artifactLocation"]);
+    let logical = |n: usize| &get(location(n), &["logicalLocations"]).as_array().unwrap()[0];
+
+    assert_eq!(str_at(&results[0], &["message", "text"]), "unsafe block in uid");
+    assert_eq!(region(0), vec![9, 5, 9, 29]);
+    assert_eq!(str_at(&results[1], &["message", "text"]), "Call in uid: `libc::getuid()`");
+    assert_eq!(str_at(artifact(1), &["uri"]), "src/lib.rs");
+    assert_eq!(str_at(artifact(1), &["uriBaseId"]), sarif::SRCROOT);
+
+    // `sys::env::var`, from its `span_info`
+    assert_eq!(str_at(&results[2], &["message", "text"]), "unsafe fn sys::env::var");
+    assert_eq!(region(2), vec![12, 1, 17, 2]);
+    assert_eq!(str_at(artifact(2), &["uri"]), "src/sys/env.rs");
+    assert_eq!(str_at(logical(2), &["name"]), "var");
+    assert_eq!(str_at(logical(2), &["fullyQualifiedName"]), "sysinfo::sys::env::var");
+
+    // The deref in `libc`'s source is at an absolute URI, not under `%SRCROOT%`
+    assert_eq!(str_at(artifact(4), &["uri"]),
+               "file:///home/me/.cargo/registry/src/libc-0.2.17/src/unix/mod.rs");
+    assert!(artifact(4).find("uriBaseId").is_none());
+    assert_eq!(str_at(&results[4], &["properties", "macroOrigin"]), "ExternalMacro");
+
+    // The one in `debug_assert!` has only a logical location
+    assert!(location(5).find("physicalLocation").is_none());
+    assert_eq!(str_at(logical(5), &["fullyQualifiedName"]), "sysinfo::sys::env::var");
+}
+
+#[test]
+fn locates_sources() {
+    assert_eq!(sarif::uri("src/lib.rs"), ("src/lib.rs".to_string(), true));
+    assert_eq!(sarif::uri("src/a b.rs"), ("src/a%20b.rs".to_string(), true));
+    assert_eq!(sarif::uri("/home/me/.cargo/registry/src/libc/src/lib.rs"),
+               ("file:///home/me/.cargo/registry/src/libc/src/lib.rs".to_string(), false));
+    assert_eq!(sarif::uri(r"C:\libc\src\lib.rs"),
+               ("file:///C:/libc/src/lib.rs".to_string(), false));
+
+    // Only absolute URIs may be `%SRCROOT%`
+    assert!(sarif::is_absolute_uri("file:///work/repo/"));
+    assert!(sarif::is_absolute_uri("https://github.com/me/repo/blob/master/"));
+    assert!(!sarif::is_absolute_uri("crates/sysinfo"));
+    assert!(!sarif::is_absolute_uri("/work/repo"));
+    assert!(!sarif::is_absolute_uri("C:/work/repo"));
+
+    let log = sarif::log(&[load(CRATE)], Some("file:///work/repo/crates/sysinfo"));
+    let run = &get(&log, &["runs"]).as_array().unwrap()[0];
+    assert_eq!(str_at(run, &["originalUriBaseIds", sarif::SRCROOT, "uri"]),
+               "file:///work/repo/crates/sysinfo/");
+}
@@ -0,0 +1,77 @@
+// Checks the summaries of the crates in `fixtures/summary/crate.json` and `fixtures/crates.json`.
+//
+// In `fixtures/summary/crate.json`, `RingBuf::push` calls `ptr::write` in an unsafe block of two
+// statements, the unsafe `RingBuf::copy_from` calls an intrinsic and dereferences a pointer in a
+// local macro, `page_size` is nothing but an unsafe block calling `sysconf`, and `RingBuf::len`
+// is safe.
+
+extern crate unsafe_ast;
+
+mod common;
+
+use common::load;
+use unsafe_ast::summary::{DEFAULT_TOP,Summary};
+
+#[test]
+fn summarizes_a_crate() {
+    let krate = load(include_str!("fixtures/summary/crate.json"));
+    let summary = Summary::new(&krate, DEFAULT_TOP);
+    assert_eq!((summary.functions(), summary.unsafe_functions(), summary.unsafe_blocks()),
+               (4, 1, 2));
+    assert_eq!(summary.unsafe_uses(), 4);
+    assert_eq!(summary.uses().get("Call"), Some(&3));
+    assert_eq!(summary.uses().get("Deref"), Some(&1));
+    // `self.grow()` is a safe call, so isn't counted
+    assert_eq!((summary.ffi_calls(), summary.intrinsic_calls(), summary.rust_calls()), (1, 1, 1));
+    assert_eq!(summary.macro_origins().get("NotMacro"), Some(&3));
+    assert_eq!(summary.macro_origins().get("LocalMacro"), Some(&1));
+    // (1/2 + 1) / 2 and (2/5 + 1) / 2
+    assert_eq!(summary.mean_block_requirement(), Some(0.75));
+    assert!((summary.mean_relative_size().unwrap() - 0.7).abs() < 1e-12);
+
+    // As dense as each other, so the one with the most uses is first
+    let densest: Vec<(&str, u64, u64)> = summary.densest().iter()
+        .map(|d| (d.function(), d.unsafe_uses(), d.size()))
+        .collect();
+    assert_eq!(densest, vec![("RingBuf::copy_from", 2, 2),
+                             ("page_size", 1, 1),
+                             ("RingBuf::push", 1, 5)]);
+    let top: Vec<String> = Summary::new(&krate, 2).densest().iter()
+        .map(|d| d.function().to_string())
+        .collect();
+    assert_eq!(top, vec!["RingBuf::copy_from", "page_size"]);
+
+    let text = summary.to_text();
+    assert!(text.starts_with("ringbuf 0.2.0 (lib)\n  functions: 4, 1 unsafe\n"));
+    assert!(text.contains("  unsafe calls: 1 FFI, 1 intrinsic, 1 Rust\n"));
+    assert!(text.contains("    LocalMacro      1\n"));
+    assert!(text.contains("  mean block requirement: 0.750\n  mean relative block size: 0.700\n"));
+    assert!(text.ends_with("    0.200  RingBuf::push (1 in 5)\n"));
+
+    let json = summary.to_json();
+    assert_eq!(json.find("unsafe_uses").and_then(|n| n.as_u64()), Some(4));
+    assert_eq!(json.find_path(&["unsafe_calls", "intrinsic"]).and_then(|n| n.as_u64()), Some(1));
+    assert_eq!(json.find_path(&["macro_origins", "LocalMacro"]).and_then(|n| n.as_u64()),
+               Some(1));
+    assert_eq!(json.find("densest").and_then(|d| d.as_array()).map(|d| d.len()), Some(3));
+}
+
+#[test]
+fn summaries_agree_with_the_analyses() {
+    // The means of `fixtures/expected/unsafe_blocks_rel_sizes.json`
+    let nested = (0.5 + 0.125 + 0.125 + 0.1111111111111111 + 0.3333333333333333
+                  + 0.2222222222222222) / 6.0;
+    let mean_relative_sizes = [Some(0.75), Some(nested), None, Some(1.0)];
+    for (line, &mean) in include_str!("fixtures/crates.json").lines().zip(&mean_relative_sizes) {
+        let summary = Summary::new(&load(line), 1);
+        match (summary.mean_relative_size(), mean) {
+            (Some(actual), Some(expected)) => assert!((actual - expected).abs() < 1e-12),
+            (actual, expected) => assert_eq!(actual, expected),
+        }
+        assert!(summary.densest().len() <= 1);
+        if summary.unsafe_uses() == 0 {
+            assert!(summary.densest().is_empty());
+            assert!(summary.to_text().contains("  mean block requirement: -\n"));
+        }
+    }
+}
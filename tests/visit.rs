@@ -0,0 +1,105 @@
+// Checks the order `UastVisitor` walks a UAST in, and the `Context` it gives each part.
+//
+// In `fixtures/visit/crate.json`, the unsafe `Buf::fill` calls `memset` and has a closure
+// dereferencing a pointer. `Buf::swap` has an unsafe block of three statements, holding a use of
+// a mutable static, an unsafe block of its own, and a closure, followed by a closure which calls
+// `f`, outside of it. `Buf::len` has nothing in it.
+
+extern crate unsafe_ast;
+
+mod common;
+
+use common::load;
+use unsafe_ast::{Block,Callee,FFI,FnDecl,Indexed,UASTNode,Unsafe};
+use unsafe_ast::visit::{self,Context,UastVisitor};
+
+/// Records each function and node visited, with the depth, whether it is in unsafe code, and the
+/// size of the innermost unsafe block around it.
+#[derive(Default)]
+struct Recorder {
+    visits: Vec<(String, usize, bool, Option<u64>)>,
+}
+
+impl<'a> UastVisitor<'a> for Recorder {
+    fn visit_fn(&mut self, function: &'a FnDecl, cx: &Context<'a>) {
+        self.visits.push((format!("fn {}", function.name()), cx.depth(), cx.in_unsafe(), None));
+        visit::walk_fn(self, function, cx)
+    }
+    fn visit_node(&mut self, node: &'a Indexed<UASTNode>, cx: &Context<'a>) {
+        assert!(cx.function().is_some());
+        self.visits.push((node.item().kind().to_string(),
+                          cx.depth(),
+                          cx.in_unsafe(),
+                          cx.unsafe_block().map(Block::size)));
+        visit::walk_node(self, node, cx)
+    }
+}
+
+fn visit(name: &str, depth: usize, in_unsafe: bool, unsafe_block: Option<u64>)
+    -> (String, usize, bool, Option<u64>)
+{
+    (name.to_string(), depth, in_unsafe, unsafe_block)
+}
+
+#[test]
+fn walks_in_order_with_context() {
+    let krate = load(include_str!("fixtures/visit/crate.json"));
+    let mut recorder = Recorder::default();
+    recorder.visit_crate(&krate);
+    assert_eq!(recorder.visits, vec![
+        visit("fn Buf::fill", 0, false, None),
+        // The body of an `unsafe fn` is unsafe, closures in it too, but isn't an unsafe block
+        visit("Call", 1, true, None),
+        visit("Closure", 1, true, None),
+        visit("Deref", 2, true, None),
+        visit("fn Buf::swap", 0, false, None),
+        visit("Call", 1, false, None),
+        visit("InnerBlock", 1, false, None),
+        visit("MutStatic", 2, true, Some(3)),
+        visit("InnerBlock", 2, true, Some(3)),
+        // The innermost unsafe block
+        visit("Deref", 3, true, Some(1)),
+        visit("Closure", 2, true, Some(3)),
+        visit("UnionFieldRead", 3, true, Some(3)),
+        // The closure is after the unsafe block, so its body is safe
+        visit("Closure", 1, false, None),
+        visit("Call", 2, false, None),
+        visit("fn Buf::len", 0, false, None),
+    ]);
+}
+
+/// Counts the calls, but doesn't look inside closures.
+#[derive(Default)]
+struct CallsOutsideClosures(usize);
+
+impl<'a> UastVisitor<'a> for CallsOutsideClosures {
+    fn visit_call(&mut self,
+                  _node: &'a Indexed<UASTNode>,
+                  _unsaf: &'a Unsafe,
+                  _ffi: &'a FFI,
+                  _callee: &'a Callee,
+                  _cx: &Context<'a>) {
+        self.0 += 1;
+    }
+    fn visit_closure(&mut self,
+                     _node: &'a Indexed<UASTNode>,
+                     _block: &'a Block,
+                     _cx: &Context<'a>) {}
+}
+
+#[test]
+fn overriding_a_method_stops_the_walk() {
+    let krate = load(include_str!("fixtures/visit/crate.json"));
+    let mut calls = CallsOutsideClosures::default();
+    calls.visit_crate(&krate);
+    // `libc::memset(..)` and `self.check()`, but not `f()` in the closure
+    assert_eq!(calls.0, 2);
+}
+
+#[test]
+fn the_default_context_is_the_crate_level() {
+    let cx = Context::default();
+    assert!(cx.function().is_none() && cx.block().is_none() && cx.unsafe_block().is_none());
+    assert_eq!(cx.depth(), 0);
+    assert!(!cx.in_unsafe());
+}